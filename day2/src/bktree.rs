@@ -0,0 +1,190 @@
+// A BK-tree over box IDs, so we can ask "what's within distance d of this ID" without rescanning
+// every stored ID the way part 2 does.
+// Each node keeps its children keyed by their distance to it. Because Levenshtein distance obeys
+// the triangle inequality, a query for ID q within d only has to descend into children whose edge
+// distance k satisfies |k - distance(q, node)| <= d.
+// Hamming isn't a metric across IDs of different lengths, so the tree is always Levenshtein.
+
+use crate::distance::{self, Metric};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+struct Node {
+    id: String,
+    chars: Vec<char>,
+    // edge distance -> index into BkTree::nodes
+    children: HashMap<usize, usize>,
+    // (parent index, edge distance), kept around so the tree can be written out as-is
+    parent: Option<(usize, usize)>,
+}
+
+#[derive(Default)]
+pub struct BkTree {
+    nodes: Vec<Node>,
+}
+
+impl BkTree {
+    pub fn new() -> BkTree {
+        BkTree::default()
+    }
+
+    pub fn from_input(input: &str) -> BkTree {
+        let mut tree = BkTree::new();
+        for line in input.lines() {
+            tree.insert(line);
+        }
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Adds an ID to the tree. Returns false if that exact ID was already stored.
+    pub fn insert(&mut self, id: &str) -> bool {
+        let chars: Vec<char> = id.chars().collect();
+        if self.nodes.is_empty() {
+            self.push(id, chars, None);
+            return true;
+        }
+        let mut current = 0;
+        loop {
+            let d = distance::distance(&self.nodes[current].chars, &chars, Metric::Levenshtein)
+                .unwrap();
+            if d == 0 {
+                return false;
+            }
+            match self.nodes[current].children.get(&d) {
+                Some(&child) => current = child,
+                None => {
+                    self.push(id, chars, Some((current, d)));
+                    return true;
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, id: &str, chars: Vec<char>, parent: Option<(usize, usize)>) {
+        let index = self.nodes.len();
+        if let Some((parent_index, d)) = parent {
+            self.nodes[parent_index].children.insert(d, index);
+        }
+        self.nodes.push(Node {
+            id: id.to_string(),
+            chars,
+            children: HashMap::new(),
+            parent,
+        });
+    }
+
+    // All stored IDs within max_distance of id, closest first (ties in insertion order).
+    pub fn query(&self, id: &str, max_distance: usize) -> Vec<(&str, usize)> {
+        let chars: Vec<char> = id.chars().collect();
+        let mut matches = Vec::new();
+        let mut to_visit = Vec::new();
+        if !self.nodes.is_empty() {
+            to_visit.push(0);
+        }
+        while let Some(index) = to_visit.pop() {
+            let node = &self.nodes[index];
+            let d = distance::distance(&node.chars, &chars, Metric::Levenshtein).unwrap();
+            if d <= max_distance {
+                matches.push((index, d));
+            }
+            let low = d.saturating_sub(max_distance);
+            let high = d + max_distance;
+            for (&edge, &child) in &node.children {
+                if edge >= low && edge <= high {
+                    to_visit.push(child);
+                }
+            }
+        }
+        matches.sort_by_key(|&(index, d)| (d, index));
+        matches
+            .into_iter()
+            .map(|(index, d)| (self.nodes[index].id.as_str(), d))
+            .collect()
+    }
+
+    // Writes one node per line as `parent<TAB>edge distance<TAB>id`, with `-` for the root's parent.
+    // Nodes are written in insertion order so parents always come before their children.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = String::new();
+        for node in &self.nodes {
+            match node.parent {
+                Some((parent, d)) => out.push_str(&format!("{}\t{}\t{}\n", parent, d, node.id)),
+                None => out.push_str(&format!("-\t-\t{}\n", node.id)),
+            }
+        }
+        fs::write(path, out)
+    }
+
+    pub fn load(path: &str) -> io::Result<BkTree> {
+        BkTree::parse(&fs::read_to_string(path)?)
+    }
+
+    // Rebuilds the tree exactly as saved, without recomputing any distances.
+    fn parse(saved: &str) -> io::Result<BkTree> {
+        let invalid = |line_number: usize| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("bad index line {}", line_number + 1),
+            )
+        };
+        let mut tree = BkTree::new();
+        for (line_number, line) in saved.lines().enumerate() {
+            let mut fields = line.splitn(3, '\t');
+            let (parent, d, id) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(parent), Some(d), Some(id)) => (parent, d, id),
+                _ => return Err(invalid(line_number)),
+            };
+            let parent = if parent == "-" && line_number == 0 {
+                None
+            } else {
+                let parent = parent.parse::<usize>().map_err(|_| invalid(line_number))?;
+                let d = d.parse::<usize>().map_err(|_| invalid(line_number))?;
+                if parent >= line_number || tree.nodes[parent].children.contains_key(&d) {
+                    return Err(invalid(line_number));
+                }
+                Some((parent, d))
+            };
+            tree.push(id, id.chars().collect(), parent);
+        }
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_INPUT: &str = "abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz";
+
+    #[test]
+    fn test_query() {
+        let mut tree = BkTree::from_input(TEST_INPUT);
+        assert_eq!(7, tree.len());
+        assert_eq!(vec![("fghij", 0), ("fguij", 1)], tree.query("fghij", 1));
+        assert_eq!(vec![("abcde", 1)], tree.query("abde", 1));
+        assert!(tree.insert("fgij"));
+        assert!(!tree.insert("fgij"));
+        assert_eq!(
+            vec![("fgij", 1), ("fghij", 2), ("fguij", 2)],
+            tree.query("fij", 2)
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let tree = BkTree::from_input(TEST_INPUT);
+        let path = std::env::temp_dir().join("day2_bktree_test.bk");
+        let path = path.to_str().unwrap();
+        tree.save(path).unwrap();
+        let loaded = BkTree::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(tree.len(), loaded.len());
+        assert_eq!(tree.query("axbye", 2), loaded.query("axbye", 2));
+        assert!(BkTree::parse("-\t-\tabc\n5\t1\tabd\n").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+// Part 1's checksum, redone for multi-million line inputs.
+// As BurntSushi pointed out, a 256 entry array is all the histogram needs. The array lives for the
+// whole chunk and gets reset by walking the line a second time, which touches far less memory than
+// zeroing all 256 slots (or allocating a HashMap) for every line.
+// The input is split into one chunk per core at line boundaries and each chunk is counted on its
+// own thread. I tried hand-vectorising the counting but the histogram scatter doesn't vectorise
+// well and the compiler already does a decent job with the tight loop, so it stays scalar.
+// NB: this counts bytes, so it matches part 1 for ASCII IDs (which is all the puzzle ever has).
+
+use std::thread;
+
+// (lines with some letter exactly twice, lines with some letter exactly three times)
+fn count_chunk(chunk: &[u8]) -> (u64, u64) {
+    let mut counts = [0u32; 256];
+    let mut doubles = 0;
+    let mut triples = 0;
+    for line in chunk.split(|&b| b == b'\n') {
+        let line = match line.last() {
+            Some(b'\r') => &line[..line.len() - 1],
+            _ => line,
+        };
+        for &b in line {
+            counts[b as usize] += 1;
+        }
+        let mut has_double = false;
+        let mut has_triple = false;
+        for &b in line {
+            match counts[b as usize] {
+                2 => has_double = true,
+                3 => has_triple = true,
+                _ => {}
+            }
+        }
+        for &b in line {
+            counts[b as usize] = 0;
+        }
+        doubles += has_double as u64;
+        triples += has_triple as u64;
+    }
+    (doubles, triples)
+}
+
+// Splits input into roughly `parts` pieces, each ending just after a newline (or at the end).
+fn split_chunks(input: &[u8], parts: usize) -> Vec<&[u8]> {
+    let target = input.len() / parts.max(1) + 1;
+    let mut chunks = Vec::with_capacity(parts);
+    let mut rest = input;
+    while !rest.is_empty() {
+        let mut end = target.min(rest.len());
+        while end < rest.len() && rest[end - 1] != b'\n' {
+            end += 1;
+        }
+        chunks.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    chunks
+}
+
+pub fn checksum(input: &str) -> u64 {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunks = split_chunks(input.as_bytes(), threads);
+    let (doubles, triples) = thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .iter()
+            .map(|chunk| scope.spawn(move || count_chunk(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .fold((0, 0), |total, (d, t)| (total.0 + d, total.1 + t))
+    });
+    doubles * triples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        let input = "abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab";
+        assert_eq!(12, checksum(input));
+        assert_eq!(12, checksum(&input.replace('\n', "\r\n")));
+        for parts in 1..10 {
+            assert_eq!(
+                input.len(),
+                split_chunks(input.as_bytes(), parts).concat().len()
+            );
+        }
+    }
+}
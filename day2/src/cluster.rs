@@ -0,0 +1,152 @@
+// Groups box IDs into families: two IDs are joined when they are within a Hamming distance
+// threshold, and a cluster is everything connected through such joins (so members of a big
+// cluster can be further apart than the threshold from each other).
+// Comparing every pair of IDs is far too slow for big lists, so candidate pairs come from buckets
+// instead: part 2's masked buckets for distance 1, and for distance d the pigeonhole principle,
+// since two IDs of the same length with at most d differences must agree exactly on at least one
+// of d + 1 blocks. Only IDs sharing a bucket get their distance checked.
+
+use crate::distance::{self, Metric};
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq)]
+pub struct Cluster {
+    // line indices, ascending
+    pub members: Vec<usize>,
+    // the chars at every position where all members agree, like part 2's answer for a pair
+    pub consensus: String,
+}
+
+// Simple union-find with path halving; clusters don't need union by rank at these sizes.
+fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+fn consensus(lines: &[Vec<char>], members: &[usize]) -> String {
+    let first = &lines[members[0]];
+    (0..first.len())
+        .filter(|&k| members.iter().all(|&m| lines[m][k] == first[k]))
+        .map(|k| first[k])
+        .collect()
+}
+
+fn join(parents: &mut [usize], a: usize, b: usize) {
+    let a = find(parents, a);
+    let b = find(parents, b);
+    if a != b {
+        parents[a.max(b)] = a.min(b);
+    }
+}
+
+// Returns every cluster, including single IDs with no neighbours, largest clusters first.
+pub fn cluster(input: &str, max_distance: usize) -> Vec<Cluster> {
+    let lines: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+    let mut parents: Vec<usize> = (0..lines.len()).collect();
+    if max_distance <= 1 {
+        // identical IDs are at distance 0, which find_near_duplicates leaves out
+        let mut first_seen: HashMap<&[char], usize> = HashMap::new();
+        for (i, line) in lines.iter().enumerate() {
+            let first = *first_seen.entry(line).or_insert(i);
+            join(&mut parents, first, i);
+        }
+        if max_distance == 1 {
+            for pair in crate::find_near_duplicates(input) {
+                join(&mut parents, pair.first, pair.second);
+            }
+        }
+    } else {
+        let blocks = max_distance + 1;
+        let mut buckets: HashMap<(usize, usize, &[char]), Vec<usize>> = HashMap::new();
+        for (i, line) in lines.iter().enumerate() {
+            let len = line.len();
+            for block in 0..blocks {
+                let (start, end) = (len * block / blocks, len * (block + 1) / blocks);
+                buckets
+                    .entry((len, block, &line[start..end]))
+                    .or_default()
+                    .push(i);
+            }
+        }
+        for indices in buckets.values() {
+            for (a, &i) in indices.iter().enumerate() {
+                for &j in &indices[a + 1..] {
+                    // already connected through other IDs (or another shared block)
+                    if find(&mut parents, i) == find(&mut parents, j) {
+                        continue;
+                    }
+                    let d = distance::distance(&lines[i], &lines[j], Metric::Hamming);
+                    if d.is_some_and(|d| d <= max_distance) {
+                        join(&mut parents, i, j);
+                    }
+                }
+            }
+        }
+    }
+    let mut groups: Vec<Vec<usize>> = vec![Vec::new(); lines.len()];
+    for i in 0..lines.len() {
+        let root = find(&mut parents, i);
+        groups[root].push(i);
+    }
+    let mut clusters: Vec<Cluster> = groups
+        .into_iter()
+        .filter(|members| !members.is_empty())
+        .map(|members| Cluster {
+            consensus: consensus(&lines, &members),
+            members,
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.members
+            .len()
+            .cmp(&a.members.len())
+            .then(a.members[0].cmp(&b.members[0]))
+    });
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster() {
+        let input = "abcde\nfghij\nabxde\nklmno\nayxde\nfguij\nabc";
+        let clusters = cluster(input, 1);
+        assert_eq!(4, clusters.len());
+        assert_eq!(vec![0, 2, 4], clusters[0].members);
+        assert_eq!("ade", clusters[0].consensus);
+        assert_eq!(vec![1, 5], clusters[1].members);
+        assert_eq!("fgij", clusters[1].consensus);
+        assert_eq!(vec![3], clusters[2].members);
+        assert_eq!("klmno", clusters[2].consensus);
+    }
+
+    #[test]
+    fn test_cluster_matches_pairwise() {
+        // every distance should give the same clusters as joining every pair find_similar finds
+        let input = "abcde\nabcde\nabxde\nayxde\nzyxde\nzyxwv\nfghij\nfguij\nqqqqq\nab\nxy";
+        for max_distance in 0..=5 {
+            let lines = input.lines().count();
+            let mut parents: Vec<usize> = (0..lines).collect();
+            for pair in distance::find_similar(input, Metric::Hamming, max_distance) {
+                join(&mut parents, pair.first, pair.second);
+            }
+            let mut expected: Vec<Vec<usize>> = vec![Vec::new(); lines];
+            for i in 0..lines {
+                let root = find(&mut parents, i);
+                expected[root].push(i);
+            }
+            expected.retain(|members| !members.is_empty());
+            let mut found: Vec<Vec<usize>> = cluster(input, max_distance)
+                .into_iter()
+                .map(|cluster| cluster.members)
+                .collect();
+            found.sort();
+            assert_eq!(expected, found, "distance {}", max_distance);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+// Part 2 only ever looked for IDs with a single substituted byte, which breaks down once you have
+// scanned IDs where a character was dropped or inserted, or IDs that aren't plain ASCII.
+// Everything in here works on Unicode scalar values (chars) rather than bytes.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    // number of positions that differ; only defined for IDs of the same length
+    Hamming,
+    // minimum number of single char insertions, deletions and substitutions
+    Levenshtein,
+}
+
+impl Metric {
+    pub fn from_name(name: &str) -> Option<Metric> {
+        match name {
+            "hamming" => Some(Metric::Hamming),
+            "levenshtein" => Some(Metric::Levenshtein),
+            _ => None,
+        }
+    }
+}
+
+// One step of the alignment that turns the first ID into the second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit {
+    Match(char),
+    Substitute(char, char),
+    // char present in the second ID but not the first
+    Insert(char),
+    // char present in the first ID but not the second
+    Delete(char),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SimilarPair {
+    pub first: usize,
+    pub second: usize,
+    pub distance: usize,
+    pub alignment: Vec<Edit>,
+}
+
+impl SimilarPair {
+    // the chars both IDs agree on, in order (same as part 2's answer for a single substitution)
+    pub fn common(&self) -> String {
+        self.alignment
+            .iter()
+            .filter_map(|edit| match edit {
+                Edit::Match(c) => Some(*c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Renders the alignment as two rows, with '-' marking gaps, e.g.
+    //   abc-de
+    //   ab-xde
+    pub fn render(&self) -> (String, String) {
+        let mut top = String::new();
+        let mut bottom = String::new();
+        for edit in &self.alignment {
+            let (a, b) = match *edit {
+                Edit::Match(c) => (c, c),
+                Edit::Substitute(a, b) => (a, b),
+                Edit::Insert(c) => ('-', c),
+                Edit::Delete(c) => (c, '-'),
+            };
+            top.push(a);
+            bottom.push(b);
+        }
+        (top, bottom)
+    }
+}
+
+fn edit_cost(alignment: &[Edit]) -> usize {
+    alignment
+        .iter()
+        .filter(|edit| !matches!(edit, Edit::Match(_)))
+        .count()
+}
+
+// Plain distance without building an alignment. Returns None for Hamming on different lengths.
+pub fn distance(a: &[char], b: &[char], metric: Metric) -> Option<usize> {
+    match metric {
+        Metric::Hamming => {
+            if a.len() != b.len() {
+                return None;
+            }
+            Some(a.iter().zip(b).filter(|(x, y)| x != y).count())
+        }
+        Metric::Levenshtein => {
+            // standard two-row DP
+            let mut previous: Vec<usize> = (0..=b.len()).collect();
+            let mut current = vec![0; b.len() + 1];
+            for (i, ca) in a.iter().enumerate() {
+                current[0] = i + 1;
+                for (j, cb) in b.iter().enumerate() {
+                    let substitution = previous[j] + if ca == cb { 0 } else { 1 };
+                    current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+                }
+                std::mem::swap(&mut previous, &mut current);
+            }
+            Some(previous[b.len()])
+        }
+    }
+}
+
+// Computes a minimum cost alignment of a onto b. Returns None for Hamming on different lengths.
+pub fn align(a: &[char], b: &[char], metric: Metric) -> Option<Vec<Edit>> {
+    match metric {
+        Metric::Hamming => {
+            if a.len() != b.len() {
+                return None;
+            }
+            Some(
+                a.iter()
+                    .zip(b)
+                    .map(|(&x, &y)| {
+                        if x == y {
+                            Edit::Match(x)
+                        } else {
+                            Edit::Substitute(x, y)
+                        }
+                    })
+                    .collect(),
+            )
+        }
+        Metric::Levenshtein => {
+            // full (a.len() + 1) x (b.len() + 1) table so we can walk back through it
+            let width = b.len() + 1;
+            let mut table = vec![0; (a.len() + 1) * width];
+            for (j, cell) in table[..width].iter_mut().enumerate() {
+                *cell = j;
+            }
+            for i in 1..=a.len() {
+                table[i * width] = i;
+                for j in 1..width {
+                    let substitution =
+                        table[(i - 1) * width + j - 1] + if a[i - 1] == b[j - 1] { 0 } else { 1 };
+                    table[i * width + j] = substitution
+                        .min(table[(i - 1) * width + j] + 1)
+                        .min(table[i * width + j - 1] + 1);
+                }
+            }
+            let mut alignment = Vec::new();
+            let (mut i, mut j) = (a.len(), b.len());
+            while i > 0 || j > 0 {
+                let here = table[i * width + j];
+                if i > 0 && j > 0 {
+                    let same = a[i - 1] == b[j - 1];
+                    if here == table[(i - 1) * width + j - 1] + if same { 0 } else { 1 } {
+                        alignment.push(if same {
+                            Edit::Match(a[i - 1])
+                        } else {
+                            Edit::Substitute(a[i - 1], b[j - 1])
+                        });
+                        i -= 1;
+                        j -= 1;
+                        continue;
+                    }
+                }
+                if i > 0 && here == table[(i - 1) * width + j] + 1 {
+                    alignment.push(Edit::Delete(a[i - 1]));
+                    i -= 1;
+                } else {
+                    alignment.push(Edit::Insert(b[j - 1]));
+                    j -= 1;
+                }
+            }
+            alignment.reverse();
+            Some(alignment)
+        }
+    }
+}
+
+// Finds every pair of lines within max_distance of each other under the given metric.
+// This is a pairwise scan, so O(N^2) comparisons, but pairs whose lengths differ by more than
+// max_distance are skipped without running the DP. Identical lines come back with distance 0.
+pub fn find_similar(input: &str, metric: Metric, max_distance: usize) -> Vec<SimilarPair> {
+    let lines: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+    let mut pairs = Vec::new();
+    for (i, a) in lines.iter().enumerate() {
+        for (j, b) in lines.iter().enumerate().skip(i + 1) {
+            let length_gap = if a.len() > b.len() {
+                a.len() - b.len()
+            } else {
+                b.len() - a.len()
+            };
+            if length_gap > max_distance || (metric == Metric::Hamming && length_gap != 0) {
+                continue;
+            }
+            match distance(a, b, metric) {
+                Some(d) if d <= max_distance => {
+                    let alignment = align(a, b, metric).unwrap();
+                    debug_assert_eq!(d, edit_cost(&alignment));
+                    pairs.push(SimilarPair {
+                        first: i,
+                        second: j,
+                        distance: d,
+                        alignment,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(
+            Some(3),
+            distance(&chars("kitten"), &chars("sitting"), Metric::Levenshtein)
+        );
+        assert_eq!(
+            None,
+            distance(&chars("kitten"), &chars("sitting"), Metric::Hamming)
+        );
+        assert_eq!(
+            Some(1),
+            distance(&chars("naïve"), &chars("naive"), Metric::Hamming)
+        );
+    }
+
+    #[test]
+    fn test_find_similar() {
+        let input = "abcde\nfghij\nabde\nxyzzy\nabcdé";
+        let pairs = find_similar(input, Metric::Levenshtein, 1);
+        let found: Vec<(usize, usize, usize)> = pairs
+            .iter()
+            .map(|pair| (pair.first, pair.second, pair.distance))
+            .collect();
+        // "abde" -> "abcdé" needs an insertion and a substitution
+        assert_eq!(vec![(0, 2, 1), (0, 4, 1)], found);
+        assert_eq!("abde", pairs[0].common());
+        assert_eq!(
+            ("abcde".to_string(), "ab-de".to_string()),
+            pairs[0].render()
+        );
+
+        let pairs = find_similar(input, Metric::Hamming, 1);
+        assert_eq!(1, pairs.len());
+        assert_eq!("abcd", pairs[0].common());
+    }
+}
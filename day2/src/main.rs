@@ -1,9 +1,20 @@
+mod bktree;
+mod checksum;
+mod cluster;
+mod distance;
+
+use bktree::BkTree;
+use distance::Metric;
 use std::collections::HashMap;
+use std::env;
 use std::fs;
+use std::io::{self, BufRead};
+use std::time::Instant;
 
 // Part 1 I did using a simple hashmap and iterative approach.
-// BurntSushi's solution takes advantage of the fact that letter counts can use an array of 256 ints instead of a full fledged HashMap. 
+// BurntSushi's solution takes advantage of the fact that letter counts can use an array of 256 ints instead of a full fledged HashMap.
 // They also use iter().any() which simplifies logic a bit.
+// This version is kept as the reference for checksum::checksum, which is what main uses now.
 fn part1(input: &str) -> u64 {
     let mut double_letter_count: u64 = 0;
     let mut triple_letter_count: u64 = 0;
@@ -37,40 +48,226 @@ fn part1(input: &str) -> u64 {
 // Part 2 asks to find two lines that differ by only 1 character.
 // The most straight-forward way I could think to do this is a standard O(N^2) loop
 // comparing each line with others. BurntSushi does the same but uses some more functional logic.
+// That falls over on really big lists though, so part 2 is now answered by find_near_duplicates below
+// and just takes the first pair it finds.
 fn part2(input: &str) -> String {
-    for (i, line1) in input.lines().enumerate() {
-        for (j, line2) in input.lines().enumerate() {
-            if i == j {
-                continue;
+    find_near_duplicates(input)
+        .into_iter()
+        .next()
+        .expect("could not find expected string")
+        .common
+}
+
+// A pair of lines which are identical except for the character at `position` (counted in chars,
+// not bytes). `first` and `second` are the (0-based) line indices, with first < second.
+#[derive(Debug, PartialEq)]
+struct NearDuplicate {
+    first: usize,
+    second: usize,
+    position: usize,
+    common: String,
+}
+
+// Polynomial hash base; hashes wrap mod 2^64.
+const HASH_BASE: u64 = 0x100_0000_01b3;
+
+// Finds every pair of lines differing in exactly one position in O(N*L) expected time.
+// The trick: two lines differ only at position p iff they are equal once p is masked out,
+// so for each p we bucket lines by (length, hash of prefix before p, hash of suffix after p).
+// Prefix hashes are computed once per line, so every masked key costs O(1) instead of hashing
+// the L bytes around p again. Lines sharing a bucket are compared byte for byte before being
+// reported, in case two different strings happened to hash the same.
+// Exact duplicates are skipped since they don't actually differ at p. Lines are compared as chars
+// rather than bytes so a differing accented letter is masked whole, not half of it.
+// Results are sorted by (first, second).
+fn find_near_duplicates(input: &str) -> Vec<NearDuplicate> {
+    let lines: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+    let max_len = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    // powers[k] = HASH_BASE^k, prefixes[i][k] = hash of lines[i][..k]
+    let mut powers = vec![1u64; max_len + 1];
+    for k in 1..=max_len {
+        powers[k] = powers[k - 1].wrapping_mul(HASH_BASE);
+    }
+    let prefixes: Vec<Vec<u64>> = lines
+        .iter()
+        .map(|line| {
+            let mut hashes = vec![0u64; line.len() + 1];
+            for (k, &c) in line.iter().enumerate() {
+                hashes[k + 1] = hashes[k].wrapping_mul(HASH_BASE).wrapping_add(c as u64 + 1);
             }
-            let line1_chars = line1.as_bytes();
-            let line2_chars = line2.as_bytes();
-            // note: all lines have same length, so short-cutting this logic
-            let mut diff = 0;
-            let mut overlapping_chars = String::new();
-            for k in 0..line1_chars.len() {
-                if line1_chars[k] != line2_chars[k] {
-                    diff += 1;
-                    if diff > 1 {
-                        break;
+            hashes
+        })
+        .collect();
+    let mut pairs = Vec::new();
+    let mut buckets: HashMap<(usize, u64, u64), Vec<usize>> = HashMap::new();
+    for position in 0..max_len {
+        buckets.clear();
+        for (i, line) in lines.iter().enumerate() {
+            if position < line.len() {
+                let hashes = &prefixes[i];
+                let suffix_len = line.len() - position - 1;
+                let suffix = hashes[line.len()]
+                    .wrapping_sub(hashes[position + 1].wrapping_mul(powers[suffix_len]));
+                buckets
+                    .entry((line.len(), hashes[position], suffix))
+                    .or_default()
+                    .push(i);
+            }
+        }
+        for indices in buckets.values() {
+            for (a, &i) in indices.iter().enumerate() {
+                for &j in &indices[a + 1..] {
+                    let (x, y) = (&lines[i], &lines[j]);
+                    if x[position] == y[position]
+                        || x[..position] != y[..position]
+                        || x[position + 1..] != y[position + 1..]
+                    {
+                        continue;
                     }
-                } else {
-                    overlapping_chars.push(line1_chars[k] as char)
+                    pairs.push(NearDuplicate {
+                        first: i,
+                        second: j,
+                        position,
+                        common: x[..position].iter().chain(&x[position + 1..]).collect(),
+                    });
                 }
             }
-            if diff == 1 {
-                return overlapping_chars;
-            }
         }
     }
-    panic!("could not find expected string")
+    pairs.sort_by_key(|pair| (pair.first, pair.second));
+    pairs
+}
+
+// Builds `count` random 26 letter IDs for benchmarking, using a little LCG so runs are repeatable.
+fn synthetic_input(count: usize) -> String {
+    let mut state: u64 = 2018;
+    let mut input = String::with_capacity(count * 27);
+    for _ in 0..count {
+        for _ in 0..26 {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            input.push((b'a' + ((state >> 33) % 26) as u8) as char);
+        }
+        input.push('\n');
+    }
+    input
 }
 
 fn main() {
     let input =
         fs::read_to_string("input/day2.txt").expect("Something went wrong reading the file");
-    println!("Day 1 Part 1: {}", part1(&input));
-    println!("Day 1 Part 2: {}", part2(&input));
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        // e.g. `cargo run -- similar levenshtein 2`
+        Some("similar") => {
+            let metric = args
+                .get(1)
+                .and_then(|name| Metric::from_name(name))
+                .expect("metric should be hamming or levenshtein");
+            let max_distance = args
+                .get(2)
+                .map(|arg| arg.parse::<usize>().expect("distance should be a number"))
+                .unwrap_or(1);
+            for pair in distance::find_similar(&input, metric, max_distance) {
+                let (top, bottom) = pair.render();
+                println!(
+                    "lines {} and {} at distance {}, common \"{}\":\n  {}\n  {}",
+                    pair.first + 1,
+                    pair.second + 1,
+                    pair.distance,
+                    pair.common(),
+                    top,
+                    bottom
+                );
+            }
+        }
+        // `cargo run -- pairs` lists every pair of IDs differing in exactly one character, not just
+        // the first one part 2 wants
+        Some("pairs") => {
+            for pair in find_near_duplicates(&input) {
+                println!(
+                    "lines {} and {} differ at character {}, common \"{}\"",
+                    pair.first + 1,
+                    pair.second + 1,
+                    pair.position + 1,
+                    pair.common
+                );
+            }
+        }
+        // `cargo run -- index ids.bk` builds an index from the puzzle input and saves it
+        Some("index") => {
+            let path = args.get(1).expect("missing index path");
+            let tree = BkTree::from_input(&input);
+            tree.save(path).expect("could not write index");
+            println!("indexed {} IDs into {}", tree.len(), path);
+        }
+        // `cargo run -- query ids.bk 2` checks each ID read from stdin against the saved index,
+        // then adds it to the index so later scans see it too
+        Some("query") => {
+            let path = args.get(1).expect("missing index path");
+            let max_distance = args
+                .get(2)
+                .map(|arg| arg.parse::<usize>().expect("distance should be a number"))
+                .unwrap_or(1);
+            let mut tree = BkTree::load(path).expect("could not read index");
+            for line in io::stdin().lock().lines() {
+                let id = line.expect("could not read stdin");
+                let matches = tree.query(&id, max_distance);
+                if matches.is_empty() {
+                    println!("{}: no stored IDs within {}", id, max_distance);
+                }
+                for (stored, d) in matches {
+                    println!("{}: {} at distance {}", id, stored, d);
+                }
+                tree.insert(&id);
+            }
+            tree.save(path).expect("could not write index");
+        }
+        // `cargo run -- cluster 2` groups IDs joined by Hamming distance <= 2
+        Some("cluster") => {
+            let max_distance = args
+                .get(1)
+                .map(|arg| arg.parse::<usize>().expect("distance should be a number"))
+                .unwrap_or(1);
+            let lines: Vec<&str> = input.lines().collect();
+            for cluster in cluster::cluster(&input, max_distance) {
+                if cluster.members.len() < 2 {
+                    continue;
+                }
+                println!(
+                    "cluster of {} with consensus \"{}\":",
+                    cluster.members.len(),
+                    cluster.consensus
+                );
+                for member in cluster.members {
+                    println!("  line {}: {}", member + 1, lines[member]);
+                }
+            }
+        }
+        // `cargo run --release -- bench 5000000` times part1 against checksum::checksum
+        Some("bench") => {
+            let count = args
+                .get(1)
+                .map(|arg| arg.parse::<usize>().expect("line count should be a number"))
+                .unwrap_or(5_000_000);
+            let input = synthetic_input(count);
+            let start = Instant::now();
+            let old = part1(&input);
+            let old_time = start.elapsed();
+            let start = Instant::now();
+            let new = checksum::checksum(&input);
+            let new_time = start.elapsed();
+            assert_eq!(old, new);
+            println!("{} lines, checksum {}", count, new);
+            println!("part1:    {:?}", old_time);
+            println!("checksum: {:?}", new_time);
+        }
+        _ => {
+            println!("Day 1 Part 1: {}", checksum::checksum(&input));
+            println!("Day 1 Part 2: {}", part2(&input));
+        }
+    }
 }
 
 #[cfg(test)]
@@ -87,4 +284,39 @@ mod tests {
         let input = "abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz";
         assert_eq!("fgij", part2(input));
     }
+
+    #[test]
+    fn test_find_near_duplicates() {
+        let input = "abcde\nfghij\nabxde\nfguij\nabcde\nabyde";
+        let pairs = find_near_duplicates(input);
+        let found: Vec<(usize, usize, usize)> = pairs
+            .iter()
+            .map(|pair| (pair.first, pair.second, pair.position))
+            .collect();
+        // the two "abcde" lines are identical, so they don't pair with each other
+        assert_eq!(
+            vec![
+                (0, 2, 2),
+                (0, 5, 2),
+                (1, 3, 2),
+                (2, 4, 2),
+                (2, 5, 2),
+                (4, 5, 2)
+            ],
+            found
+        );
+        assert_eq!("abde", pairs[0].common);
+        assert_eq!("fgij", pairs[2].common);
+
+        // é and ë are two bytes each, differing only in the second
+        let pairs = find_near_duplicates("héllo\nhëllo\nhello");
+        let found: Vec<(usize, usize, usize, &str)> = pairs
+            .iter()
+            .map(|pair| (pair.first, pair.second, pair.position, pair.common.as_str()))
+            .collect();
+        assert_eq!(
+            vec![(0, 1, 1, "hllo"), (0, 2, 1, "hllo"), (1, 2, 1, "hllo")],
+            found
+        );
+    }
 }
@@ -0,0 +1,146 @@
+// Which claims overlap which, and by how much, so the elves can work out who has to move.
+// Part 2 just wants the one claim with no edges; this keeps the whole graph.
+
+use crate::Claim;
+
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    // indices into the claims the graph was built from, with first < second
+    pub first: usize,
+    pub second: usize,
+    pub area: usize,
+}
+
+pub struct ConflictGraph<'a> {
+    claims: &'a [Claim],
+    pub conflicts: Vec<Conflict>,
+    // claim index -> indices of the conflicts it is part of
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl<'a> ConflictGraph<'a> {
+    // Sorts claims by left edge and only compares each claim against the ones that start before
+    // it ends, so this is O(n log n) plus the number of pairs that share an x range.
+    pub fn build(claims: &'a [Claim]) -> ConflictGraph<'a> {
+        let mut order: Vec<usize> = (0..claims.len()).collect();
+        order.sort_by_key(|&i| claims[i].left);
+        let mut conflicts = Vec::new();
+        for (k, &i) in order.iter().enumerate() {
+            for &j in &order[k + 1..] {
+                if claims[j].left >= claims[i].right() {
+                    break;
+                }
+                let area = claims[i].overlap(&claims[j]);
+                if area > 0 {
+                    conflicts.push(Conflict {
+                        first: i.min(j),
+                        second: i.max(j),
+                        area,
+                    });
+                }
+            }
+        }
+        conflicts.sort_by_key(|conflict| (conflict.first, conflict.second));
+        let mut adjacency = vec![Vec::new(); claims.len()];
+        for (index, conflict) in conflicts.iter().enumerate() {
+            adjacency[conflict.first].push(index);
+            adjacency[conflict.second].push(index);
+        }
+        ConflictGraph {
+            claims,
+            conflicts,
+            adjacency,
+        }
+    }
+
+    // (other claim index, overlap area) for every claim overlapping claim `index`
+    pub fn neighbours(&self, index: usize) -> Vec<(usize, usize)> {
+        self.adjacency[index]
+            .iter()
+            .map(|&c| {
+                let conflict = &self.conflicts[c];
+                let other = if conflict.first == index {
+                    conflict.second
+                } else {
+                    conflict.first
+                };
+                (other, conflict.area)
+            })
+            .collect()
+    }
+
+    // how many claims overlap claim `index`, without collecting them
+    pub fn degree(&self, index: usize) -> usize {
+        self.adjacency[index].len()
+    }
+
+    // ids of every claim that doesn't overlap anything (part 2 is the first of these)
+    pub fn isolated(&self) -> Vec<usize> {
+        (0..self.claims.len())
+            .filter(|&i| self.adjacency[i].is_empty())
+            .map(|i| self.claims[i].id)
+            .collect()
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("graph conflicts {\n");
+        for claim in self.claims {
+            out.push_str(&format!("  {};\n", claim.id));
+        }
+        for conflict in &self.conflicts {
+            out.push_str(&format!(
+                "  {} -- {} [label=\"{}\"];\n",
+                self.claims[conflict.first].id, self.claims[conflict.second].id, conflict.area
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let claims: Vec<String> = (0..self.claims.len())
+            .map(|i| {
+                let overlaps: Vec<String> = self
+                    .neighbours(i)
+                    .into_iter()
+                    .map(|(other, area)| {
+                        format!("{{\"id\":{},\"area\":{}}}", self.claims[other].id, area)
+                    })
+                    .collect();
+                format!(
+                    "{{\"id\":{},\"overlaps\":[{}]}}",
+                    self.claims[i].id,
+                    overlaps.join(",")
+                )
+            })
+            .collect();
+        let isolated: Vec<String> = self.isolated().iter().map(|id| id.to_string()).collect();
+        format!(
+            "{{\"claims\":[{}],\"isolated\":[{}]}}\n",
+            claims.join(","),
+            isolated.join(",")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_input;
+
+    #[test]
+    fn test_conflict_graph() {
+        let claims = parse_input("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n#4 @ 2,4: 1x1");
+        let graph = ConflictGraph::build(&claims);
+        assert_eq!(vec![(1, 4), (3, 1)], graph.neighbours(0));
+        assert_eq!(vec![3], graph.isolated());
+        assert_eq!(
+            "graph conflicts {\n  1;\n  2;\n  3;\n  4;\n  1 -- 2 [label=\"4\"];\n  1 -- 4 [label=\"1\"];\n}\n",
+            graph.to_dot()
+        );
+        assert_eq!(
+            "{\"claims\":[{\"id\":1,\"overlaps\":[{\"id\":2,\"area\":4},{\"id\":4,\"area\":1}]},{\"id\":2,\"overlaps\":[{\"id\":1,\"area\":4}]},{\"id\":3,\"overlaps\":[]},{\"id\":4,\"overlaps\":[{\"id\":1,\"area\":1}]}],\"isolated\":[3]}\n",
+            graph.to_json()
+        );
+    }
+}
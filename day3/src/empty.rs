@@ -0,0 +1,192 @@
+// Where could a new claim go? This finds the largest rectangles of fabric that nobody has claimed.
+// The x and y coordinates are compressed down to the claim edges (plus the fabric bounds), so the
+// fabric becomes a grid of variable sized blocks that are each wholly claimed or wholly free. Every
+// maximal empty rectangle has its edges on those lines, so running the classic "largest rectangle
+// in a histogram" stack over each row of blocks finds them all, in O(n^2) for n claims however big
+// the fabric is.
+
+use crate::Claim;
+use std::cmp::Reverse;
+
+pub const DEFAULT_SIZE: usize = 1000;
+
+#[derive(Debug, PartialEq)]
+pub struct EmptyRect {
+    pub left: usize,
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl EmptyRect {
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+}
+
+fn compress(edges: impl Iterator<Item = usize>, size: usize) -> Vec<usize> {
+    let mut coords: Vec<usize> = edges.map(|v| v.min(size)).chain(vec![0, size]).collect();
+    coords.sort_unstable();
+    coords.dedup();
+    coords
+}
+
+// The `count` largest maximal empty rectangles inside a `width` x `height` fabric, biggest first
+// (ties broken top to bottom, then left to right, then wider first).
+// Claims sticking out of the fabric are clipped.
+pub fn largest_empty(
+    claims: &[Claim],
+    width: usize,
+    height: usize,
+    count: usize,
+) -> Vec<EmptyRect> {
+    let claims: Vec<&Claim> = claims
+        .iter()
+        .filter(|claim| {
+            claim.width > 0 && claim.height > 0 && claim.left < width && claim.top < height
+        })
+        .collect();
+    let xs = compress(claims.iter().flat_map(|c| vec![c.left, c.right()]), width);
+    let ys = compress(claims.iter().flat_map(|c| vec![c.top, c.bottom()]), height);
+    let (cols, rows) = (xs.len() - 1, ys.len() - 1);
+    if cols == 0 || rows == 0 {
+        return Vec::new();
+    }
+
+    // 2D difference array over the blocks, then prefix sums to get how many claims cover each
+    let mut cover = vec![0isize; (rows + 1) * (cols + 1)];
+    let index = |coords: &[usize], v: usize| {
+        coords
+            .binary_search(&v.min(*coords.last().unwrap()))
+            .unwrap()
+    };
+    for claim in &claims {
+        let (c0, c1) = (index(&xs, claim.left), index(&xs, claim.right()));
+        let (r0, r1) = (index(&ys, claim.top), index(&ys, claim.bottom()));
+        cover[r0 * (cols + 1) + c0] += 1;
+        cover[r0 * (cols + 1) + c1] -= 1;
+        cover[r1 * (cols + 1) + c0] -= 1;
+        cover[r1 * (cols + 1) + c1] += 1;
+    }
+    for r in 0..rows {
+        for c in 0..cols {
+            let mut value = cover[r * (cols + 1) + c];
+            if r > 0 {
+                value += cover[(r - 1) * (cols + 1) + c];
+            }
+            if c > 0 {
+                value += cover[r * (cols + 1) + c - 1];
+            }
+            if r > 0 && c > 0 {
+                value -= cover[(r - 1) * (cols + 1) + c - 1];
+            }
+            cover[r * (cols + 1) + c] = value;
+        }
+    }
+    let free = |r: usize, c: usize| cover[r * (cols + 1) + c] == 0;
+
+    let mut found = Vec::new();
+    // heights[c] is how far the free run in column c reaches up from the bottom of the current row
+    let mut heights = vec![0; cols];
+    // claimed blocks in the next row, as prefix counts, to check a rectangle can't grow downwards
+    let mut below = vec![0; cols + 1];
+    for r in 0..rows {
+        let row_height = ys[r + 1] - ys[r];
+        for (c, h) in heights.iter_mut().enumerate() {
+            *h = if free(r, c) { *h + row_height } else { 0 };
+        }
+        for c in 0..cols {
+            below[c + 1] = below[c] + if r + 1 < rows && free(r + 1, c) { 0 } else { 1 };
+        }
+        let mut stack: Vec<usize> = Vec::new();
+        for c in 0..=cols {
+            let h = if c < cols { heights[c] } else { 0 };
+            while let Some(&top) = stack.last() {
+                if heights[top] < h {
+                    break;
+                }
+                stack.pop();
+                // the stack is strictly increasing, so the bar popped reaches left to just past
+                // the one under it, and if the current bar is just as tall it can still grow right
+                let left_col = stack.last().map_or(0, |&s| s + 1);
+                let maximal_sideways = heights[top] > h;
+                let blocked_below = below[c] - below[left_col] > 0;
+                if heights[top] > 0 && maximal_sideways && blocked_below {
+                    found.push(EmptyRect {
+                        left: xs[left_col],
+                        top: ys[r + 1] - heights[top],
+                        width: xs[c] - xs[left_col],
+                        height: heights[top],
+                    });
+                }
+            }
+            stack.push(c);
+        }
+    }
+    found.sort_by_key(|rect| {
+        (
+            Reverse(rect.area()),
+            rect.top,
+            rect.left,
+            Reverse(rect.width),
+        )
+    });
+    found.truncate(count);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_input;
+
+    #[test]
+    fn test_largest_empty() {
+        let claims = parse_input("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2");
+        let found = largest_empty(&claims, 8, 8, 5);
+        assert_eq!(
+            vec![
+                EmptyRect {
+                    left: 0,
+                    top: 0,
+                    width: 3,
+                    height: 3
+                },
+                EmptyRect {
+                    left: 0,
+                    top: 0,
+                    width: 8,
+                    height: 1
+                },
+                EmptyRect {
+                    left: 0,
+                    top: 0,
+                    width: 1,
+                    height: 8
+                },
+                EmptyRect {
+                    left: 7,
+                    top: 0,
+                    width: 1,
+                    height: 8
+                },
+                EmptyRect {
+                    left: 0,
+                    top: 7,
+                    width: 8,
+                    height: 1
+                },
+            ],
+            found
+        );
+        assert_eq!(
+            vec![EmptyRect {
+                left: 0,
+                top: 0,
+                width: 10,
+                height: 10
+            }],
+            largest_empty(&[], 10, 10, 5)
+        );
+    }
+}
@@ -0,0 +1,218 @@
+// A fabric that keeps part 1's overlap area and part 2's clean claims up to date as claims are
+// added, removed or resized one at a time, for editing claims live.
+// Each claim remembers which other claims it overlaps. When a claim comes or goes, the only area
+// that changes depth is inside it: cells covered by exactly one of its neighbours flip between
+// "claimed once" and "overlapping". So the overlap delta is the exactly-once area of the neighbours
+// clipped to the claim, which sweep::depth_histogram gives us without touching every cell.
+// Finding the neighbours is a scan over the claims, so each edit is O(n + k log k) for a claim with
+// k neighbours, rather than redoing part 1 from scratch.
+
+use crate::sweep;
+use crate::Claim;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Default)]
+pub struct Fabric {
+    claims: BTreeMap<usize, Claim>,
+    neighbours: BTreeMap<usize, BTreeSet<usize>>,
+    overlap_area: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Change {
+    // how much the area covered by two or more claims grew (or shrank, if negative)
+    pub overlap_delta: isize,
+    // ids of claims that are now clean but weren't before (including newly added clean claims)
+    pub became_clean: Vec<usize>,
+    // ids of claims that now overlap something but didn't before
+    pub became_conflicted: Vec<usize>,
+}
+
+impl Fabric {
+    pub fn new() -> Fabric {
+        Fabric::default()
+    }
+
+    pub fn from_claims(claims: Vec<Claim>) -> Fabric {
+        let mut fabric = Fabric::new();
+        for claim in claims {
+            fabric.replace(claim);
+        }
+        fabric
+    }
+
+    pub fn overlap_area(&self) -> usize {
+        self.overlap_area
+    }
+
+    // ids of every claim that overlaps nothing, ascending
+    pub fn clean_claims(&self) -> Vec<usize> {
+        self.neighbours
+            .iter()
+            .filter(|(_, others)| others.is_empty())
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    // Area inside `claim` covered by exactly one of the given other claims.
+    fn covered_once_within(&self, claim: &Claim, others: &BTreeSet<usize>) -> usize {
+        let clipped: Vec<Claim> = others
+            .iter()
+            .map(|id| {
+                let other = &self.claims[id];
+                let left = claim.left.max(other.left);
+                let top = claim.top.max(other.top);
+                Claim {
+                    id: *id,
+                    left,
+                    top,
+                    width: claim.right().min(other.right()) - left,
+                    height: claim.bottom().min(other.bottom()) - top,
+                }
+            })
+            .collect();
+        let histogram = sweep::depth_histogram(&clipped);
+        histogram.exactly.get(1).cloned().unwrap_or(0)
+    }
+
+    // Some(true) for clean, Some(false) for conflicted, None if not on the fabric.
+    fn status(&self, id: usize) -> Option<bool> {
+        self.neighbours.get(&id).map(|others| others.is_empty())
+    }
+
+    // Runs an edit and works out which of the `affected` claims changed status.
+    fn tracked<F: FnOnce(&mut Fabric) -> isize>(
+        &mut self,
+        affected: BTreeSet<usize>,
+        edit: F,
+    ) -> Change {
+        let before: Vec<Option<bool>> = affected.iter().map(|&id| self.status(id)).collect();
+        let overlap_delta = edit(self);
+        let mut change = Change {
+            overlap_delta,
+            ..Change::default()
+        };
+        for (&id, before) in affected.iter().zip(before) {
+            match (before, self.status(id)) {
+                (Some(true), Some(false)) | (None, Some(false)) => {
+                    change.became_conflicted.push(id)
+                }
+                (Some(false), Some(true)) | (None, Some(true)) => change.became_clean.push(id),
+                _ => {}
+            }
+        }
+        change
+    }
+
+    fn overlapping(&self, claim: &Claim) -> BTreeSet<usize> {
+        self.claims
+            .values()
+            .filter(|other| other.id != claim.id && claim.overlap(other) > 0)
+            .map(|other| other.id)
+            .collect()
+    }
+
+    fn insert(&mut self, claim: Claim) -> isize {
+        let others = self.overlapping(&claim);
+        let delta = self.covered_once_within(&claim, &others);
+        for other in &others {
+            self.neighbours.get_mut(other).unwrap().insert(claim.id);
+        }
+        self.neighbours.insert(claim.id, others);
+        self.claims.insert(claim.id, claim);
+        self.overlap_area += delta;
+        delta as isize
+    }
+
+    fn delete(&mut self, id: usize) -> isize {
+        let others = self.neighbours.remove(&id).unwrap_or_default();
+        let claim = match self.claims.remove(&id) {
+            Some(claim) => claim,
+            None => return 0,
+        };
+        for other in &others {
+            self.neighbours.get_mut(other).unwrap().remove(&id);
+        }
+        let delta = self.covered_once_within(&claim, &others);
+        self.overlap_area -= delta;
+        -(delta as isize)
+    }
+
+    // Puts a claim on the fabric, replacing any claim with the same id.
+    fn replace(&mut self, claim: Claim) -> Change {
+        let mut affected = self.overlapping(&claim);
+        affected.insert(claim.id);
+        if let Some(existing) = self.neighbours.get(&claim.id) {
+            affected.extend(existing);
+        }
+        self.tracked(affected, |fabric| {
+            fabric.delete(claim.id) + fabric.insert(claim)
+        })
+    }
+
+    // Adds a claim, or returns None if there's already a claim with that id.
+    pub fn add(&mut self, claim: Claim) -> Option<Change> {
+        if self.claims.contains_key(&claim.id) {
+            return None;
+        }
+        Some(self.replace(claim))
+    }
+
+    // Removes a claim, or returns None if there's no claim with that id.
+    pub fn remove(&mut self, id: usize) -> Option<Change> {
+        let affected = self.neighbours.get(&id)?.clone();
+        Some(self.tracked(affected, |fabric| fabric.delete(id)))
+    }
+
+    // Moves and/or resizes an existing claim, or returns None if there's no claim with that id.
+    pub fn resize(
+        &mut self,
+        id: usize,
+        left: usize,
+        top: usize,
+        width: usize,
+        height: usize,
+    ) -> Option<Change> {
+        if !self.claims.contains_key(&id) {
+            return None;
+        }
+        Some(self.replace(Claim {
+            id,
+            left,
+            top,
+            width,
+            height,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_input;
+
+    #[test]
+    fn test_fabric() {
+        let mut fabric = Fabric::from_claims(parse_input("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4"));
+        assert_eq!(4, fabric.overlap_area());
+        assert!(fabric.clean_claims().is_empty());
+
+        let change = fabric.add(parse_input("#3 @ 4,4: 3x3").remove(0)).unwrap();
+        assert_eq!(4, change.overlap_delta);
+        assert_eq!(vec![3], change.became_conflicted);
+        assert_eq!(8, fabric.overlap_area());
+
+        let change = fabric.remove(1).unwrap();
+        assert_eq!(-5, change.overlap_delta);
+        assert!(change.became_clean.is_empty());
+        assert_eq!(3, fabric.overlap_area());
+
+        let change = fabric.resize(3, 10, 10, 2, 2).unwrap();
+        assert_eq!(-3, change.overlap_delta);
+        assert_eq!(vec![2, 3], change.became_clean);
+        assert_eq!(vec![2, 3], fabric.clean_claims());
+        assert_eq!(0, fabric.overlap_area());
+        assert_eq!(None, fabric.remove(1));
+        assert_eq!(None, fabric.add(parse_input("#2 @ 0,0: 1x1").remove(0)));
+    }
+}
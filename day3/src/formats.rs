@@ -0,0 +1,325 @@
+// Claims in the formats our other tools use, alongside the puzzle's `#id @ x,y: wxh` lines:
+//   CSV with `id,left,top,width,height` columns (the header line is optional), and
+//   JSON as an array of `{"id": 1, "left": 2, "top": 3, "width": 4, "height": 5}` objects.
+// There's no serde here; the JSON reader only handles that shape (plus whitespace), which is all
+// we ever write or get sent.
+
+use crate::{Claim, CLAIM_PATTERN};
+use regex::Regex;
+use std::fs;
+
+const FIELDS: [&str; 5] = ["id", "left", "top", "width", "height"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Puzzle,
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn from_path(path: &str) -> Option<Format> {
+        let extension = path.rsplit('.').next()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "txt" => Some(Format::Puzzle),
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    // Best guess from the file itself, for when the extension doesn't say.
+    pub fn detect(content: &str) -> Format {
+        match content.trim_start().chars().next() {
+            Some('[') => Format::Json,
+            Some('#') => Format::Puzzle,
+            _ => Format::Csv,
+        }
+    }
+}
+
+fn fields(claim: &Claim) -> [usize; 5] {
+    [claim.id, claim.left, claim.top, claim.width, claim.height]
+}
+
+fn from_fields(values: [usize; 5]) -> Claim {
+    Claim {
+        id: values[0],
+        left: values[1],
+        top: values[2],
+        width: values[3],
+        height: values[4],
+    }
+}
+
+pub fn write(claims: &[Claim], format: Format) -> String {
+    match format {
+        Format::Puzzle => claims
+            .iter()
+            .map(|c| {
+                format!(
+                    "#{} @ {},{}: {}x{}\n",
+                    c.id, c.left, c.top, c.width, c.height
+                )
+            })
+            .collect(),
+        Format::Csv => {
+            let mut out = FIELDS.join(",") + "\n";
+            for claim in claims {
+                let values: Vec<String> = fields(claim).iter().map(|v| v.to_string()).collect();
+                out.push_str(&values.join(","));
+                out.push('\n');
+            }
+            out
+        }
+        Format::Json => {
+            let objects: Vec<String> = claims
+                .iter()
+                .map(|claim| {
+                    let pairs: Vec<String> = FIELDS
+                        .iter()
+                        .zip(fields(claim).iter())
+                        .map(|(name, value)| format!("\"{}\": {}", name, value))
+                        .collect();
+                    format!("  {{{}}}", pairs.join(", "))
+                })
+                .collect();
+            format!("[\n{}\n]\n", objects.join(",\n"))
+        }
+    }
+}
+
+pub fn read(content: &str, format: Format) -> Result<Vec<Claim>, String> {
+    match format {
+        Format::Puzzle => read_puzzle(content),
+        Format::Csv => read_csv(content),
+        Format::Json => read_json(content),
+    }
+}
+
+fn read_puzzle(content: &str) -> Result<Vec<Claim>, String> {
+    let re = Regex::new(CLAIM_PATTERN).unwrap();
+    let mut claims = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let cap = re
+            .captures(line.trim_end())
+            .ok_or_else(|| format!("line {}: not a claim", index + 1))?;
+        let mut values = [0; 5];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = cap[i + 1]
+                .parse()
+                .map_err(|_| format!("line {}: number too large", index + 1))?;
+        }
+        claims.push(from_fields(values));
+    }
+    Ok(claims)
+}
+
+fn read_csv(content: &str) -> Result<Vec<Claim>, String> {
+    let mut claims = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (index == 0 && line.replace(' ', "") == FIELDS.join(",")) {
+            continue;
+        }
+        let columns: Vec<&str> = line.split(',').map(str::trim).collect();
+        if columns.len() != FIELDS.len() {
+            return Err(format!(
+                "line {}: expected {} columns, found {}",
+                index + 1,
+                FIELDS.len(),
+                columns.len()
+            ));
+        }
+        let mut values = [0; 5];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = columns[i]
+                .parse()
+                .map_err(|_| format!("line {}: bad {} {:?}", index + 1, FIELDS[i], columns[i]))?;
+        }
+        claims.push(from_fields(values));
+    }
+    Ok(claims)
+}
+
+// Just enough of a JSON reader for an array of flat objects with whole number values.
+struct JsonReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> JsonReader<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.position < self.bytes.len() && self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("byte {}: {}", self.position, message)
+    }
+
+    // Consumes `expected` (after any whitespace) if it's next, and says whether it was.
+    fn eat(&mut self, expected: u8) -> bool {
+        self.skip_whitespace();
+        if self.bytes.get(self.position) == Some(&expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), String> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected as char)))
+        }
+    }
+
+    fn string(&mut self) -> Result<&'a str, String> {
+        self.expect(b'"')?;
+        let start = self.position;
+        while self.position < self.bytes.len() && self.bytes[self.position] != b'"' {
+            if self.bytes[self.position] == b'\\' {
+                return Err(self.error("escapes aren't supported in keys"));
+            }
+            self.position += 1;
+        }
+        let key = std::str::from_utf8(&self.bytes[start..self.position])
+            .map_err(|_| self.error("key isn't valid UTF-8"))?;
+        self.expect(b'"')?;
+        Ok(key)
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        self.skip_whitespace();
+        let start = self.position;
+        while self.position < self.bytes.len() && self.bytes[self.position].is_ascii_digit() {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.position])
+            .unwrap()
+            .parse()
+            .map_err(|_| {
+                self.position = start;
+                self.error("expected a whole number")
+            })
+    }
+
+    fn claim(&mut self) -> Result<Claim, String> {
+        self.expect(b'{')?;
+        let mut values: [Option<usize>; 5] = [None; 5];
+        if !self.eat(b'}') {
+            loop {
+                let key = self.string()?;
+                self.expect(b':')?;
+                let value = self.number()?;
+                if let Some(i) = FIELDS.iter().position(|&field| field == key) {
+                    values[i] = Some(value);
+                }
+                if self.eat(b'}') {
+                    break;
+                }
+                self.expect(b',')?;
+            }
+        }
+        let mut fields = [0; 5];
+        for (i, value) in values.iter().enumerate() {
+            fields[i] =
+                value.ok_or_else(|| self.error(&format!("claim is missing \"{}\"", FIELDS[i])))?;
+        }
+        Ok(from_fields(fields))
+    }
+}
+
+fn read_json(content: &str) -> Result<Vec<Claim>, String> {
+    let mut reader = JsonReader {
+        bytes: content.as_bytes(),
+        position: 0,
+    };
+    let mut claims = Vec::new();
+    reader.expect(b'[')?;
+    if !reader.eat(b']') {
+        loop {
+            claims.push(reader.claim()?);
+            if reader.eat(b']') {
+                break;
+            }
+            reader.expect(b',')?;
+        }
+    }
+    reader.skip_whitespace();
+    if reader.position != reader.bytes.len() {
+        return Err(reader.error("unexpected content after the claims"));
+    }
+    Ok(claims)
+}
+
+// Reads a claim file of any format, going by the extension first and the content second.
+fn read_file(path: &str) -> Result<(String, Format), String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let format = Format::from_path(path).unwrap_or_else(|| Format::detect(&content));
+    Ok((content, format))
+}
+
+pub fn load(path: &str) -> Result<(Vec<Claim>, Format), String> {
+    let (content, format) = read_file(path)?;
+    let claims = read(&content, format).map_err(|e| format!("{}: {}", path, e))?;
+    Ok((claims, format))
+}
+
+// A claim file as puzzle format text. Puzzle files come back untouched, bad lines and all, so
+// they can still be checked line by line; the others are converted.
+pub fn load_puzzle(path: &str) -> Result<String, String> {
+    match read_file(path)? {
+        (content, Format::Puzzle) => Ok(content),
+        (content, format) => {
+            let claims = read(&content, format).map_err(|e| format!("{}: {}", path, e))?;
+            Ok(write(&claims, Format::Puzzle))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_INPUT: &str = "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n";
+
+    #[test]
+    fn test_round_trip() {
+        let claims = read(TEST_INPUT, Format::Puzzle).unwrap();
+        let csv = write(&claims, Format::Csv);
+        assert_eq!(
+            "id,left,top,width,height\n1,1,3,4,4\n2,3,1,4,4\n3,5,5,2,2\n",
+            csv
+        );
+        let json = write(&claims, Format::Json);
+        assert_eq!(Format::Json, Format::detect(&json));
+        assert_eq!(Format::Csv, Format::detect(&csv));
+        for (text, format) in [(csv, Format::Csv), (json, Format::Json)] {
+            let back = read(&text, format).unwrap();
+            assert_eq!(TEST_INPUT, write(&back, Format::Puzzle));
+        }
+        let json = "[{\"height\":2,\"width\":2,\"top\":5,\"left\":5,\"id\":3,\"colour\":7}]";
+        assert_eq!(
+            "#3 @ 5,5: 2x2\n",
+            write(&read(json, Format::Json).unwrap(), Format::Puzzle)
+        );
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert_eq!(
+            Err("line 2: bad width \"x\"".to_string()),
+            read("1,1,3,4,4\n2,3,1,x,4", Format::Csv).map(|_| ())
+        );
+        assert_eq!(
+            Err("byte 9: claim is missing \"left\"".to_string()),
+            read("[{\"id\":1}]", Format::Json).map(|_| ())
+        );
+        assert!(read("[{\"id\":-1}]", Format::Json).is_err());
+    }
+}
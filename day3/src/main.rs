@@ -1,6 +1,25 @@
+mod conflicts;
+mod empty;
+mod fabric;
+mod formats;
+mod render;
+mod rtree;
+mod sweep;
+mod validate;
+mod withdraw;
+
+use conflicts::ConflictGraph;
+use fabric::Fabric;
+use formats::Format;
+use render::{Heatmap, RenderOptions};
+use rtree::ClaimIndex;
+use withdraw::Objective;
+
 use regex::Regex;
-use std::collections::HashMap;
+use std::env;
 use std::fs;
+use std::io::{self, BufRead};
+use std::process;
 
 #[derive(Debug)]
 struct Claim {
@@ -11,81 +30,319 @@ struct Claim {
     height: usize,
 }
 
-fn parse_line(line: &str) -> Claim {
-    let re = Regex::new(r"^#(\d+) @ (\d+),(\d+): (\d+)x(\d+)$").unwrap();
-    let cap = re.captures(line).unwrap();
-    Claim {
-        id: cap[1].parse::<usize>().unwrap(),
-        left: cap[2].parse::<usize>().unwrap(),
-        top: cap[3].parse::<usize>().unwrap(),
-        width: cap[4].parse::<usize>().unwrap(),
-        height: cap[5].parse::<usize>().unwrap(),
+impl Claim {
+    fn right(&self) -> usize {
+        self.left + self.width
+    }
+
+    fn bottom(&self) -> usize {
+        self.top + self.height
+    }
+
+    // number of square inches this claim shares with another
+    fn overlap(&self, other: &Claim) -> usize {
+        let width = self
+            .right()
+            .min(other.right())
+            .saturating_sub(self.left.max(other.left));
+        let height = self
+            .bottom()
+            .min(other.bottom())
+            .saturating_sub(self.top.max(other.top));
+        width * height
     }
 }
 
-type Grid = HashMap<(usize, usize), usize>;
-
-fn part1(input: &str) -> (usize, Grid) {
-    // "dumb" brute-force way to do this is to actually create the 1000x1000 grid and paint inside it
-    // I found this nice snippet so will just keep it here for future reference:
-
-    // let mut grid_raw = vec![0; grid_size * grid_size];
-    // let mut grid_base: Vec<_> = grid_raw.as_mut_slice().chunks_mut(grid_size).collect();
-    // let grid = grid_base.as_mut_slice();
-    // println!("grid = {:?}", grid);
-
-    // more performant way is to just treat each visit as tuple and store that in a map
-    // (I'm sure there might be a better mathy way, but don't feel like doing it that way)
-    // let mut visited: HashSet<(usize, usize)> = HashSet::new();
-    let mut overlaps = 0;
-    let mut visited = Grid::new();
-    for line in input.lines() {
-        let claim = parse_line(line);
-        for x in claim.left..claim.left + claim.width {
-            for y in claim.top..claim.top + claim.height {
-                // I purposely don't use visited.entry(..).or_insert(0) += 1 here
-                // because I wanted to avoid the second iteration of the hashmap at end
-                if visited.contains_key(&(x, y)) {
-                    let current_val = *visited.get(&(x, y)).unwrap();
-                    if current_val == 1 {
-                        // only count overlap once
-                        overlaps += 1;
-                    }
-                    visited.insert((x, y), current_val + 1);
-                } else {
-                    visited.insert((x, y), 1);
-                }
+const CLAIM_PATTERN: &str = r"^#(\d+) @ (\d+),(\d+): (\d+)x(\d+)$";
+
+// Trusts every line and panics on a bad one, so main goes through validate instead and this is
+// for the tests.
+#[cfg(test)]
+fn parse_input(input: &str) -> Vec<Claim> {
+    let re = Regex::new(CLAIM_PATTERN).unwrap();
+    input
+        .lines()
+        .map(|line| {
+            let cap = re.captures(line).unwrap();
+            Claim {
+                id: cap[1].parse::<usize>().unwrap(),
+                left: cap[2].parse::<usize>().unwrap(),
+                top: cap[3].parse::<usize>().unwrap(),
+                width: cap[4].parse::<usize>().unwrap(),
+                height: cap[5].parse::<usize>().unwrap(),
             }
+        })
+        .collect()
+}
+
+fn part1(claims: &[Claim]) -> usize {
+    // "dumb" brute-force way to do this is to actually create the 1000x1000 grid and paint inside it,
+    // and the first version here stored each visited square inch in a HashMap instead. Both cost a
+    // cell per square inch, so now the area comes from the sweep line in sweep::overlap_area,
+    // which only looks at claim edges.
+    sweep::overlap_area(claims)
+}
+
+// The claim that doesn't overlap anything, found by comparing claim rectangles with each other
+// rather than painting a grid. None if every claim overlaps another one.
+fn part2(claims: &[Claim]) -> Option<usize> {
+    ConflictGraph::build(claims).isolated().first().cloned()
+}
+
+// `cargo run -- owner` answers queries from stdin, one per line:
+//   `x,y` for the claims covering that square inch,
+//   `x,y: wxh` for the claims intersecting that rectangle, and
+//   `near x,y` for the closest claim
+fn owner(claims: &[Claim]) {
+    let index = ClaimIndex::build(claims);
+    let query_regex =
+        Regex::new(r"^(?P<near>near )?(?P<x>\d+),(?P<y>\d+)(: (?P<w>\d+)x(?P<h>\d+))?$").unwrap();
+    for line in io::stdin().lock().lines() {
+        let line = line.expect("could not read stdin");
+        let cap = match query_regex.captures(line.trim()) {
+            Some(cap) => cap,
+            None => {
+                println!("unrecognised query: {}", line);
+                continue;
+            }
+        };
+        let x = cap["x"].parse::<usize>().unwrap();
+        let y = cap["y"].parse::<usize>().unwrap();
+        if cap.name("near").is_some() {
+            match index.nearest(x, y) {
+                Some((id, d)) => println!("nearest claim #{} (squared distance {})", id, d),
+                None => println!("no claims"),
+            }
+        } else if let (Some(w), Some(h)) = (cap.name("w"), cap.name("h")) {
+            let w = w.as_str().parse::<usize>().unwrap();
+            let h = h.as_str().parse::<usize>().unwrap();
+            println!("{:?}", index.intersecting(x, y, w, h));
+        } else {
+            println!("{:?}", index.at(x, y));
         }
     }
-    (overlaps, visited)
 }
 
-fn part2(input: &str, grid: &Grid) -> usize {
-    for line in input.lines() {
-        let claim = parse_line(line);
-        let mut has_overlaps = false;
-        for x in claim.left..claim.left + claim.width {
-            for y in claim.top..claim.top + claim.height {
-                if *grid.get(&(x, y)).unwrap() != 1 {
-                    has_overlaps = true;
-                    break;
+// `cargo run -- edit` starts from the puzzle input and applies edits from stdin, one per line:
+//   `#id @ x,y: wxh` adds a claim, or moves/resizes it if the id is already there, and
+//   `remove id` takes a claim away, and
+//   `clean` lists the claims that don't overlap anything
+fn edit(claims: Vec<Claim>) {
+    let mut fabric = Fabric::from_claims(claims);
+    println!("overlap area {}", fabric.overlap_area());
+    let claim_regex = Regex::new(CLAIM_PATTERN).unwrap();
+    for line in io::stdin().lock().lines() {
+        let line = line.expect("could not read stdin");
+        let line = line.trim();
+        let change = if let Some(id) = line.strip_prefix("remove ") {
+            id.trim_start_matches('#')
+                .parse::<usize>()
+                .ok()
+                .and_then(|id| fabric.remove(id))
+        } else if line == "clean" {
+            println!("clean claims: {:?}", fabric.clean_claims());
+            continue;
+        } else {
+            match validate::check_claim(&claim_regex, line) {
+                Ok(claim) => {
+                    match fabric.resize(claim.id, claim.left, claim.top, claim.width, claim.height)
+                    {
+                        Some(change) => Some(change),
+                        None => fabric.add(claim),
+                    }
+                }
+                Err(problem) => {
+                    println!("couldn't apply: {} ({})", line, problem);
+                    continue;
                 }
             }
+        };
+        match change {
+            Some(change) => println!(
+                "overlap area {} ({:+}), now clean: {:?}, now conflicted: {:?}",
+                fabric.overlap_area(),
+                change.overlap_delta,
+                change.became_clean,
+                change.became_conflicted
+            ),
+            None => println!("couldn't apply: {}", line),
         }
-        if !has_overlaps {
-            return claim.id;
+    }
+}
+
+// Fabric size from a `WxH` argument, 1000x1000 if not given.
+fn parse_size(arg: Option<&String>) -> (usize, usize) {
+    match arg {
+        Some(size) => {
+            let mut parts = size
+                .split('x')
+                .map(|v| v.parse::<usize>().expect("size should be WxH"));
+            (
+                parts.next().unwrap(),
+                parts.next().expect("size should be WxH"),
+            )
         }
+        None => (empty::DEFAULT_SIZE, empty::DEFAULT_SIZE),
     }
-    panic!("didn't find any claims without overlaps");
 }
 
 fn main() {
-    let input =
-        fs::read_to_string("input/day3.txt").expect("Something went wrong reading the file");
-    let (overlapping_count, grid) = part1(&input);
-    println!("Day 3 Part 1: {}", overlapping_count);
-    println!("Day 3 Part 2: {}", part2(&input, &grid));
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // `cargo run -- convert claims.csv claims.json` converts between the puzzle format, CSV and JSON
+    if args.first().map(String::as_str) == Some("convert") {
+        let (from, to) = match (args.get(1), args.get(2)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => panic!("usage: convert <from> <to>"),
+        };
+        let (claims, _) = formats::load(from).unwrap_or_else(|e| panic!("{}", e));
+        let format = Format::from_path(to).expect("output should end in .txt, .csv or .json");
+        fs::write(to, formats::write(&claims, format)).expect("could not write claims");
+        return;
+    }
+    // `--input claims.json` (anywhere in the arguments) analyses another claim file instead, in any
+    // of the formats. Everything below works on puzzle format text, so other formats are converted.
+    let input = match args.iter().position(|arg| arg == "--input") {
+        Some(i) if i + 1 < args.len() => {
+            args.remove(i);
+            let path = args.remove(i);
+            formats::load_puzzle(&path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            })
+        }
+        Some(_) => {
+            eprintln!("usage: --input <path>");
+            process::exit(2);
+        }
+        None => {
+            fs::read_to_string("input/day3.txt").expect("Something went wrong reading the file")
+        }
+    };
+    // `cargo run -- validate 1000x1000 clean.txt` checks the input against a fabric of that size,
+    // and writes the usable claims (clipped to the fabric) to clean.txt, .csv or .json if given.
+    if args.first().map(String::as_str) == Some("validate") {
+        let (width, height) = parse_size(args.get(1));
+        let (claims, issues) = validate::validate(&input, width, height);
+        for issue in &issues {
+            println!("{}", issue);
+        }
+        println!("{} usable claims, {} problems", claims.len(), issues.len());
+        if let Some(to) = args.get(2) {
+            let format = Format::from_path(to).expect("output should end in .txt, .csv or .json");
+            fs::write(to, formats::write(&claims, format)).expect("could not write claims");
+        }
+        if !issues.is_empty() {
+            process::exit(1);
+        }
+        return;
+    }
+    // Everything else runs on whatever claims are usable, rather than panicking on the first bad
+    // line. There's no fabric size here, so nothing counts as out of bounds.
+    let (claims, issues) = validate::validate(&input, usize::MAX, usize::MAX);
+    if !issues.is_empty() {
+        for issue in &issues {
+            eprintln!("{}", issue);
+        }
+        eprintln!("skipping {} unusable lines", issues.len());
+    }
+    match args.first().map(String::as_str) {
+        // `cargo run -- conflicts dot` (or json) dumps the claim conflict graph
+        Some("conflicts") => {
+            let graph = ConflictGraph::build(&claims);
+            match args.get(1).map(String::as_str) {
+                Some("json") => print!("{}", graph.to_json()),
+                _ => print!("{}", graph.to_dot()),
+            }
+        }
+        // `cargo run -- withdraw area` (or count) suggests claims to withdraw so nothing overlaps
+        Some("withdraw") => {
+            let objective = match args.get(1).map(String::as_str) {
+                Some("area") => Objective::Area,
+                _ => Objective::Count,
+            };
+            let result = withdraw::withdraw(&claims, objective, withdraw::EXACT_LIMIT);
+            println!(
+                "withdraw {} claims (cost {}, best possible at least {}): {:?}",
+                result.claims.len(),
+                result.cost,
+                result.lower_bound,
+                result.claims
+            );
+        }
+        // `cargo run -- empty 5 1000x1000` lists the 5 largest unclaimed rectangles
+        Some("empty") => {
+            let count = args
+                .get(1)
+                .map(|arg| arg.parse::<usize>().expect("count should be a number"))
+                .unwrap_or(1);
+            let (width, height) = parse_size(args.get(2));
+            for rect in empty::largest_empty(&claims, width, height, count) {
+                println!(
+                    "{}x{} at {},{} ({} sq in)",
+                    rect.width,
+                    rect.height,
+                    rect.left,
+                    rect.top,
+                    rect.area()
+                );
+            }
+        }
+        // `cargo run -- render fabric.png --outlines` writes a heatmap (png, ppm or svg by extension)
+        // and `cargo run -- render term 120` prints one 120 characters wide. Only the SVG can label
+        // the outlines with claim ids, so the others print a legend of where each outlined claim is.
+        Some("render") => {
+            let target = args.get(1).expect("missing output path (or term)");
+            let options = RenderOptions {
+                outlines: args.iter().any(|arg| arg == "--outlines"),
+                highlight: part2(&claims),
+            };
+            let heatmap = Heatmap::build(&claims);
+            if target == "term" {
+                let columns = args
+                    .get(2)
+                    .and_then(|arg| arg.parse::<usize>().ok())
+                    .unwrap_or(100);
+                print!("{}", heatmap.to_ansi(&claims, &options, columns));
+            } else {
+                let bytes = if target.ends_with(".svg") {
+                    heatmap.to_svg(&claims, &options).into_bytes()
+                } else if target.ends_with(".ppm") {
+                    heatmap.to_ppm(&claims, &options)
+                } else {
+                    heatmap.to_png(&claims, &options)
+                };
+                fs::write(target, bytes).expect("could not write image");
+                if !target.ends_with(".svg") {
+                    print!("{}", heatmap.legend(&claims, &options));
+                }
+            }
+        }
+        Some("owner") => owner(&claims),
+        Some("edit") => edit(claims),
+        // `cargo run -- depth` shows how deep the claims pile up
+        Some("depth") => {
+            let histogram = sweep::depth_histogram(&claims);
+            if let Some((x, y)) = histogram.max_location {
+                println!(
+                    "Worst pile-up: {} claims deep at {},{}",
+                    histogram.max_depth, x, y
+                );
+            }
+            for k in 1..=histogram.max_depth {
+                println!(
+                    "  depth {}: exactly {} sq in, at least {} sq in",
+                    k, histogram.exactly[k], histogram.at_least[k]
+                );
+            }
+        }
+        _ => {
+            println!("Day 3 Part 1: {}", part1(&claims));
+            match part2(&claims) {
+                Some(id) => println!("Day 3 Part 2: {}", id),
+                None => println!("Day 3 Part 2: every claim overlaps another"),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -93,20 +350,21 @@ mod tests {
     use super::*;
     #[test]
     fn test_part1() {
-        assert_eq!(4, part1("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2").0);
+        let claims = parse_input("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2");
+        assert_eq!(4, part1(&claims));
     }
 
     // #[test]
     // fn test_part1_full_file() {
     //     let input =
     //         fs::read_to_string("input/day3.txt").expect("Something went wrong reading the file");
-    //     assert_eq!(115348, part1(&input));
+    //     assert_eq!(115348, part1(&parse_input(&input)));
     // }
 
     #[test]
     fn test_part2() {
-        let input = "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2";
-        let (_, grid) = part1(&input);
-        assert_eq!(3, part2(&input, &grid));
+        let claims = parse_input("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2");
+        assert_eq!(Some(3), part2(&claims));
+        assert_eq!(None, part2(&claims[..2]));
     }
 }
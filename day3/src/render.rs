@@ -0,0 +1,373 @@
+// Pictures of the fabric: every square inch coloured by how many claims cover it, optionally with
+// each claim's outline, and the clean claim from part 2 picked out in green.
+// PPM and PNG are written by hand (the PNG just uses uncompressed deflate blocks) so this doesn't
+// need an image crate, SVG gets the claim ids as labels too, and the terminal version shrinks the
+// fabric down and prints it as coloured blocks. Pixels have no room for text, so PPM, PNG and the
+// terminal come with a legend saying where each outlined claim is instead.
+
+use crate::Claim;
+
+type Rgb = (u8, u8, u8);
+
+const BACKGROUND: Rgb = (24, 24, 24);
+const OUTLINE: Rgb = (255, 255, 255);
+const HIGHLIGHT: Rgb = (0, 230, 64);
+
+#[derive(Debug, Default)]
+pub struct RenderOptions {
+    // draw the edge of every claim
+    pub outlines: bool,
+    // id of a claim to outline in green, normally the clean one from part 2
+    pub highlight: Option<usize>,
+}
+
+// Claim depth for every square inch from 0,0 to the far edges of the claims. This is one cell per
+// square inch, so it's meant for puzzle sized fabrics rather than ones in the millions.
+pub struct Heatmap {
+    pub width: usize,
+    pub height: usize,
+    depth: Vec<usize>,
+    pub max_depth: usize,
+}
+
+impl Heatmap {
+    pub fn build(claims: &[Claim]) -> Heatmap {
+        let width = claims.iter().map(|c| c.right()).max().unwrap_or(0);
+        let height = claims.iter().map(|c| c.bottom()).max().unwrap_or(0);
+        // same 2D difference array trick as empty.rs, just at full resolution
+        let mut depth = vec![0isize; (width + 1) * (height + 1)];
+        let stride = width + 1;
+        for claim in claims {
+            depth[claim.top * stride + claim.left] += 1;
+            depth[claim.top * stride + claim.right()] -= 1;
+            depth[claim.bottom() * stride + claim.left] -= 1;
+            depth[claim.bottom() * stride + claim.right()] += 1;
+        }
+        let mut cells = vec![0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let mut value = depth[y * stride + x];
+                if y > 0 {
+                    value += depth[(y - 1) * stride + x];
+                }
+                if x > 0 {
+                    value += depth[y * stride + x - 1];
+                }
+                if x > 0 && y > 0 {
+                    value -= depth[(y - 1) * stride + x - 1];
+                }
+                depth[y * stride + x] = value;
+                cells[y * width + x] = value as usize;
+            }
+        }
+        let max_depth = cells.iter().cloned().max().unwrap_or(0);
+        Heatmap {
+            width,
+            height,
+            depth: cells,
+            max_depth,
+        }
+    }
+
+    pub fn depth(&self, x: usize, y: usize) -> usize {
+        self.depth[y * self.width + x]
+    }
+
+    // Unclaimed is near black, claimed once is blue, and overlaps run from yellow to red.
+    fn colour(&self, depth: usize) -> Rgb {
+        match depth {
+            0 => BACKGROUND,
+            1 => (40, 90, 200),
+            _ => {
+                let steps = self.max_depth.max(3) - 2;
+                let t = (depth - 2) as f64 / steps as f64;
+                (255, (220.0 * (1.0 - t)) as u8, 0)
+            }
+        }
+    }
+
+    // Full resolution RGB pixels, row by row, with outlines drawn on top.
+    fn pixels(&self, claims: &[Claim], options: &RenderOptions) -> Vec<Rgb> {
+        let mut pixels: Vec<Rgb> = self.depth.iter().map(|&d| self.colour(d)).collect();
+        let mut outline = |claim: &Claim, colour: Rgb| {
+            if claim.width == 0 || claim.height == 0 {
+                return;
+            }
+            for x in claim.left..claim.right() {
+                pixels[claim.top * self.width + x] = colour;
+                pixels[(claim.bottom() - 1) * self.width + x] = colour;
+            }
+            for y in claim.top..claim.bottom() {
+                pixels[y * self.width + claim.left] = colour;
+                pixels[y * self.width + claim.right() - 1] = colour;
+            }
+        };
+        if options.outlines {
+            for claim in claims {
+                outline(claim, OUTLINE);
+            }
+        }
+        if let Some(claim) = claims.iter().find(|c| Some(c.id) == options.highlight) {
+            outline(claim, HIGHLIGHT);
+        }
+        pixels
+    }
+
+    pub fn to_ppm(&self, claims: &[Claim], options: &RenderOptions) -> Vec<u8> {
+        let mut out = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        for (r, g, b) in self.pixels(claims, options) {
+            out.extend_from_slice(&[r, g, b]);
+        }
+        out
+    }
+
+    pub fn to_png(&self, claims: &[Claim], options: &RenderOptions) -> Vec<u8> {
+        let pixels = self.pixels(claims, options);
+        // each scanline starts with filter type 0 (none)
+        let mut raw = Vec::with_capacity(self.height * (3 * self.width + 1));
+        for row in pixels.chunks(self.width.max(1)) {
+            raw.push(0);
+            for &(r, g, b) in row {
+                raw.extend_from_slice(&[r, g, b]);
+            }
+        }
+        let mut header = Vec::new();
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // 8 bits per channel, colour type 2 (RGB), default compression/filter, no interlace
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        png_chunk(&mut out, b"IHDR", &header);
+        png_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+        png_chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    // One line per outlined claim with its id and where to find it, for the formats that can't
+    // label claims themselves.
+    pub fn legend(&self, claims: &[Claim], options: &RenderOptions) -> String {
+        let mut out = String::new();
+        for claim in outlined(claims, options) {
+            out.push_str(&format!(
+                "#{} at {},{} ({}x{}){}\n",
+                claim.id,
+                claim.left,
+                claim.top,
+                claim.width,
+                claim.height,
+                clean_note(claim, options)
+            ));
+        }
+        out
+    }
+
+    // Runs of equal depth along each row become one rect, then claims go on top as outlines.
+    pub fn to_svg(&self, claims: &[Claim], options: &RenderOptions) -> String {
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" shape-rendering=\"crispEdges\">\n",
+            self.width, self.height
+        );
+        out.push_str(&format!(
+            "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+            self.width,
+            self.height,
+            hex(BACKGROUND)
+        ));
+        for y in 0..self.height {
+            let mut x = 0;
+            while x < self.width {
+                let depth = self.depth(x, y);
+                let start = x;
+                while x < self.width && self.depth(x, y) == depth {
+                    x += 1;
+                }
+                if depth > 0 {
+                    out.push_str(&format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"{}\"/>\n",
+                        start,
+                        y,
+                        x - start,
+                        hex(self.colour(depth))
+                    ));
+                }
+            }
+        }
+        for claim in outlined(claims, options) {
+            let highlighted = Some(claim.id) == options.highlight;
+            let colour = if highlighted { HIGHLIGHT } else { OUTLINE };
+            out.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"0.5\"/>\n",
+                claim.left, claim.top, claim.width, claim.height, hex(colour)
+            ));
+            out.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" font-size=\"4\" fill=\"{}\">#{}</text>\n",
+                claim.left + 1,
+                claim.top + 4,
+                hex(colour),
+                claim.id
+            ));
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    // Squashes the fabric into `columns` characters across using 24-bit ANSI colours. Each
+    // character shows the deepest square inch in its block, and terminal cells are about twice as
+    // tall as they are wide so each one covers twice as many rows as columns. The legend below
+    // gives the character column and row where each outlined claim starts.
+    pub fn to_ansi(&self, claims: &[Claim], options: &RenderOptions, columns: usize) -> String {
+        let pixels = self.pixels(claims, options);
+        let block = self.width.div_ceil(columns.max(1)).max(1);
+        let mut out = String::new();
+        for by in (0..self.height).step_by(2 * block) {
+            for bx in (0..self.width).step_by(block) {
+                // prefer highlight, then outline, then the deepest cell
+                let mut best: Option<(usize, Rgb)> = None;
+                for y in by..(by + 2 * block).min(self.height) {
+                    for x in bx..(bx + block).min(self.width) {
+                        let colour = pixels[y * self.width + x];
+                        let rank = if colour == HIGHLIGHT {
+                            usize::MAX
+                        } else if colour == OUTLINE {
+                            usize::MAX - 1
+                        } else {
+                            self.depth(x, y)
+                        };
+                        if best.is_none_or(|(r, _)| rank > r) {
+                            best = Some((rank, colour));
+                        }
+                    }
+                }
+                let (r, g, b) = best.map_or(BACKGROUND, |(_, colour)| colour);
+                out.push_str(&format!("\x1b[48;2;{};{};{}m ", r, g, b));
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        for claim in outlined(claims, options) {
+            out.push_str(&format!(
+                "#{} at column {}, row {}{}\n",
+                claim.id,
+                claim.left / block + 1,
+                claim.top / (2 * block) + 1,
+                clean_note(claim, options)
+            ));
+        }
+        out
+    }
+}
+
+// The claims that get an outline: all of them with --outlines, otherwise just the highlighted one.
+fn outlined<'a>(
+    claims: &'a [Claim],
+    options: &'a RenderOptions,
+) -> impl Iterator<Item = &'a Claim> {
+    claims
+        .iter()
+        .filter(move |claim| options.outlines || Some(claim.id) == options.highlight)
+}
+
+fn clean_note(claim: &Claim, options: &RenderOptions) -> &'static str {
+    if Some(claim.id) == options.highlight {
+        ", the clean one in green"
+    } else {
+        ""
+    }
+}
+
+fn hex((r, g, b): Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// A zlib stream made of uncompressed deflate blocks (at most 65535 bytes each).
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = if data.is_empty() {
+        vec![data]
+    } else {
+        data.chunks(65535).collect()
+    };
+    for (i, block) in blocks.iter().enumerate() {
+        out.push(if i + 1 == blocks.len() { 1 } else { 0 });
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_input;
+
+    #[test]
+    fn test_heatmap() {
+        let claims = parse_input("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2");
+        let heatmap = Heatmap::build(&claims);
+        assert_eq!(
+            (7, 7, 2),
+            (heatmap.width, heatmap.height, heatmap.max_depth)
+        );
+        assert_eq!(0, heatmap.depth(0, 0));
+        assert_eq!(2, heatmap.depth(4, 4));
+        assert_eq!(1, heatmap.depth(6, 6));
+
+        let options = RenderOptions {
+            outlines: false,
+            highlight: Some(3),
+        };
+        let ppm = heatmap.to_ppm(&claims, &options);
+        assert!(ppm.starts_with(b"P6\n7 7\n255\n"));
+        assert_eq!(11 + 7 * 7 * 3, ppm.len());
+        let pixel = |x: usize, y: usize| &ppm[11 + 3 * (y * 7 + x)..11 + 3 * (y * 7 + x + 1)];
+        assert_eq!(&[0, 230, 64], pixel(5, 5));
+        assert_eq!(&[255, 220, 0], pixel(4, 4));
+
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        let png = heatmap.to_png(&claims, &options);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+
+        let svg = heatmap.to_svg(&claims, &options);
+        assert!(svg.contains("<text x=\"6\" y=\"9\" font-size=\"4\" fill=\"#00e640\">#3</text>"));
+        assert_eq!(
+            "#3 at 5,5 (2x2), the clean one in green\n",
+            heatmap.legend(&claims, &options)
+        );
+        let ansi = heatmap.to_ansi(&claims, &options, 7);
+        assert_eq!(5, ansi.lines().count());
+        assert!(ansi.ends_with("#3 at column 6, row 3, the clean one in green\n"));
+    }
+}
@@ -0,0 +1,230 @@
+// A static R-tree over the claims, so "who owns this spot" doesn't mean walking every claim.
+// It's bulk loaded with Sort-Tile-Recursive packing: sort the boxes by x, cut them into vertical
+// slices, sort each slice by y and pack runs of NODE_SIZE into nodes, then repeat one level up
+// until a single root is left.
+
+use crate::Claim;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+const NODE_SIZE: usize = 8;
+
+// Half-open box of square inches: left..right by top..bottom.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rect {
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+}
+
+impl Rect {
+    fn of(claim: &Claim) -> Rect {
+        Rect {
+            left: claim.left,
+            top: claim.top,
+            right: claim.right(),
+            bottom: claim.bottom(),
+        }
+    }
+
+    fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        self.left < other.right
+            && other.left < self.right
+            && self.top < other.bottom
+            && other.top < self.bottom
+    }
+
+    // squared distance from square inch (x, y) to the nearest square inch inside this box
+    fn distance_squared(&self, x: usize, y: usize) -> usize {
+        let gap = |v: usize, low: usize, high: usize| {
+            if v < low {
+                low - v
+            } else if v >= high {
+                v + 1 - high
+            } else {
+                0
+            }
+        };
+        let dx = gap(x, self.left, self.right);
+        let dy = gap(y, self.top, self.bottom);
+        dx * dx + dy * dy
+    }
+
+    fn centre(&self) -> (usize, usize) {
+        (
+            self.left + (self.right - self.left) / 2,
+            self.top + (self.bottom - self.top) / 2,
+        )
+    }
+}
+
+struct Node {
+    bounds: Rect,
+    // indices into ClaimIndex::nodes, or into the claims for leaves
+    children: Vec<usize>,
+    leaf: bool,
+}
+
+pub struct ClaimIndex<'a> {
+    claims: &'a [Claim],
+    nodes: Vec<Node>,
+}
+
+// Packs the given (bounds, item) pairs into nodes, returning the new node indices.
+fn pack(nodes: &mut Vec<Node>, mut items: Vec<(Rect, usize)>, leaf: bool) -> Vec<usize> {
+    let node_count = items.len().div_ceil(NODE_SIZE);
+    let slices = (node_count as f64).sqrt().ceil() as usize;
+    let slice_size = slices * NODE_SIZE;
+    items.sort_by_key(|(bounds, _)| bounds.centre().0);
+    let mut packed = Vec::with_capacity(node_count);
+    for slice in items.chunks_mut(slice_size.max(1)) {
+        slice.sort_by_key(|(bounds, _)| bounds.centre().1);
+        for run in slice.chunks(NODE_SIZE) {
+            let bounds = run
+                .iter()
+                .skip(1)
+                .fold(run[0].0, |acc, (bounds, _)| acc.union(bounds));
+            packed.push(nodes.len());
+            nodes.push(Node {
+                bounds,
+                children: run.iter().map(|&(_, item)| item).collect(),
+                leaf,
+            });
+        }
+    }
+    packed
+}
+
+impl<'a> ClaimIndex<'a> {
+    pub fn build(claims: &'a [Claim]) -> ClaimIndex<'a> {
+        let mut nodes = Vec::new();
+        // claims with no area don't cover anything, so they never get returned
+        let items: Vec<(Rect, usize)> = claims
+            .iter()
+            .enumerate()
+            .filter(|(_, claim)| claim.width > 0 && claim.height > 0)
+            .map(|(i, claim)| (Rect::of(claim), i))
+            .collect();
+        let mut level = pack(&mut nodes, items, true);
+        while level.len() > 1 {
+            let items = level.iter().map(|&n| (nodes[n].bounds, n)).collect();
+            level = pack(&mut nodes, items, false);
+        }
+        // the root is always last, so the search can start from nodes.len() - 1
+        ClaimIndex { claims, nodes }
+    }
+
+    // Collects every claim whose box intersects `area`, as ids in ascending order.
+    fn search(&self, area: &Rect) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut to_visit: Vec<usize> = self.nodes.len().checked_sub(1).into_iter().collect();
+        while let Some(n) = to_visit.pop() {
+            let node = &self.nodes[n];
+            if !node.bounds.intersects(area) {
+                continue;
+            }
+            if node.leaf {
+                found.extend(
+                    node.children
+                        .iter()
+                        .filter(|&&c| Rect::of(&self.claims[c]).intersects(area))
+                        .map(|&c| self.claims[c].id),
+                );
+            } else {
+                to_visit.extend(&node.children);
+            }
+        }
+        found.sort_unstable();
+        found
+    }
+
+    // ids of the claims covering square inch (x, y)
+    pub fn at(&self, x: usize, y: usize) -> Vec<usize> {
+        self.search(&Rect {
+            left: x,
+            top: y,
+            right: x + 1,
+            bottom: y + 1,
+        })
+    }
+
+    // ids of the claims sharing at least one square inch with the given rectangle
+    pub fn intersecting(&self, left: usize, top: usize, width: usize, height: usize) -> Vec<usize> {
+        self.search(&Rect {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        })
+    }
+
+    // The claim closest to square inch (x, y) (0 if it covers it) and its squared distance.
+    // Best-first search: nodes come off the heap in order of their distance lower bound, so the
+    // first claim to come off is the nearest. Ties go to the lowest id.
+    pub fn nearest(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let root = self.nodes.len().checked_sub(1)?;
+        // (distance, is a claim rather than a node, id or node index)
+        let mut heap = BinaryHeap::new();
+        heap.push(Reverse((
+            self.nodes[root].bounds.distance_squared(x, y),
+            false,
+            root,
+        )));
+        while let Some(Reverse((distance, is_claim, item))) = heap.pop() {
+            if is_claim {
+                return Some((item, distance));
+            }
+            let node = &self.nodes[item];
+            for &c in &node.children {
+                let entry = if node.leaf {
+                    let claim = &self.claims[c];
+                    (Rect::of(claim).distance_squared(x, y), true, claim.id)
+                } else {
+                    (self.nodes[c].bounds.distance_squared(x, y), false, c)
+                };
+                heap.push(Reverse(entry));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_input;
+
+    #[test]
+    fn test_claim_index() {
+        let mut input = String::from("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n");
+        // enough extra claims to force a few levels in the tree
+        for i in 0..200 {
+            input.push_str(&format!(
+                "#{} @ {},{}: 3x3\n",
+                i + 4,
+                100 + (i % 20) * 10,
+                100 + (i / 20) * 10
+            ));
+        }
+        let claims = parse_input(&input);
+        let index = ClaimIndex::build(&claims);
+        assert_eq!(vec![1, 2], index.at(4, 4));
+        assert_eq!(vec![3], index.at(6, 6));
+        assert!(index.at(0, 0).is_empty());
+        assert_eq!(vec![1, 2, 3], index.intersecting(4, 4, 2, 2));
+        assert_eq!(vec![4, 5], index.intersecting(102, 100, 10, 1));
+        assert_eq!(Some((3, 0)), index.nearest(6, 6));
+        assert_eq!(Some((3, 18)), index.nearest(9, 9));
+        assert_eq!(Some((27, 2)), index.nearest(133, 113));
+    }
+}
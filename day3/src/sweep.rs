@@ -0,0 +1,271 @@
+// The "mathy way" I skipped in part 1: sweep a vertical line across the fabric from left to right,
+// and keep a segment tree over the (compressed) y coordinates that knows how much of the line is
+// currently covered by at least k claims. Each claim adds its y range when the line reaches its
+// left edge and removes it at its right edge, so the work is O(n log n) in the number of claims
+// no matter how big they are.
+
+use crate::Claim;
+
+// Segment tree over the elementary intervals ys[i]..ys[i + 1].
+// `count[node]` is how many claims cover the node's whole range (without being pushed down), and
+// `covered[node][k]` is the length within the node covered by at least k claims, for k up to `cap`.
+// Depths above `cap` are lumped in with `cap`.
+pub struct CoverageTree {
+    ys: Vec<usize>,
+    cap: usize,
+    count: Vec<usize>,
+    covered: Vec<Vec<usize>>,
+}
+
+impl CoverageTree {
+    pub fn new(ys: Vec<usize>, cap: usize) -> CoverageTree {
+        let size = 4 * ys.len().max(1);
+        CoverageTree {
+            ys,
+            cap,
+            count: vec![0; size],
+            covered: vec![vec![0; cap + 1]; size],
+        }
+    }
+
+    // Adds (delta = 1) or removes (delta = -1) one claim over y range [top, bottom).
+    pub fn update(&mut self, top: usize, bottom: usize, delta: isize) {
+        let lo = self.ys.binary_search(&top).unwrap();
+        let hi = self.ys.binary_search(&bottom).unwrap();
+        if lo < hi && self.ys.len() > 1 {
+            self.update_node(1, 0, self.ys.len() - 1, lo, hi, delta);
+        }
+    }
+
+    fn update_node(
+        &mut self,
+        node: usize,
+        start: usize,
+        end: usize,
+        lo: usize,
+        hi: usize,
+        delta: isize,
+    ) {
+        if hi <= start || end <= lo {
+            return;
+        }
+        if lo <= start && end <= hi {
+            self.count[node] = (self.count[node] as isize + delta) as usize;
+        } else {
+            let mid = (start + end) / 2;
+            self.update_node(2 * node, start, mid, lo, hi, delta);
+            self.update_node(2 * node + 1, mid, end, lo, hi, delta);
+        }
+        self.pull(node, start, end);
+    }
+
+    fn pull(&mut self, node: usize, start: usize, end: usize) {
+        let full = self.ys[end] - self.ys[start];
+        let here = self.count[node];
+        for k in 0..=self.cap {
+            self.covered[node][k] = if k <= here {
+                full
+            } else if end - start == 1 {
+                0
+            } else {
+                self.covered[2 * node][k - here] + self.covered[2 * node + 1][k - here]
+            };
+        }
+    }
+
+    // Length of the sweep line currently covered by at least k claims (k <= cap).
+    pub fn covered(&self, k: usize) -> usize {
+        if self.ys.len() > 1 {
+            self.covered[1][k]
+        } else {
+            0
+        }
+    }
+}
+
+// Range add / global max over the elementary y intervals, used to find the deepest pile-up.
+struct MaxTree {
+    size: usize,
+    add: Vec<isize>,
+    max: Vec<isize>,
+}
+
+impl MaxTree {
+    fn new(size: usize) -> MaxTree {
+        MaxTree {
+            size,
+            add: vec![0; 4 * size.max(1)],
+            max: vec![0; 4 * size.max(1)],
+        }
+    }
+
+    fn update(
+        &mut self,
+        node: usize,
+        start: usize,
+        end: usize,
+        lo: usize,
+        hi: usize,
+        delta: isize,
+    ) {
+        if hi <= start || end <= lo {
+            return;
+        }
+        if lo <= start && end <= hi {
+            self.add[node] += delta;
+            self.max[node] += delta;
+            return;
+        }
+        let mid = (start + end) / 2;
+        self.update(2 * node, start, mid, lo, hi, delta);
+        self.update(2 * node + 1, mid, end, lo, hi, delta);
+        self.max[node] = self.add[node] + self.max[2 * node].max(self.max[2 * node + 1]);
+    }
+
+    // (max depth, index of the first elementary interval reaching it)
+    fn argmax(&self) -> (usize, usize) {
+        let (mut node, mut start, mut end) = (1, 0, self.size);
+        let target = self.max[1];
+        let mut remaining = target;
+        while end - start > 1 {
+            remaining -= self.add[node];
+            let mid = (start + end) / 2;
+            if self.max[2 * node] == remaining {
+                node *= 2;
+                end = mid;
+            } else {
+                node = 2 * node + 1;
+                start = mid;
+            }
+        }
+        (target as usize, start)
+    }
+}
+
+// (x, delta, top, bottom): the sweep line reaching a claim's left (+1) or right (-1) edge
+pub type Event = (usize, isize, usize, usize);
+
+// Claims turned into events sorted by x, plus the sorted distinct ys.
+pub fn events(claims: &[Claim]) -> (Vec<Event>, Vec<usize>) {
+    let mut events = Vec::with_capacity(2 * claims.len());
+    let mut ys = Vec::with_capacity(2 * claims.len());
+    for claim in claims {
+        if claim.width == 0 || claim.height == 0 {
+            continue;
+        }
+        let bottom = claim.top + claim.height;
+        events.push((claim.left, 1, claim.top, bottom));
+        events.push((claim.left + claim.width, -1, claim.top, bottom));
+        ys.push(claim.top);
+        ys.push(bottom);
+    }
+    events.sort_unstable();
+    ys.sort_unstable();
+    ys.dedup();
+    (events, ys)
+}
+
+// Same answer as part 1, the area covered by two or more claims.
+pub fn overlap_area(claims: &[Claim]) -> usize {
+    let (events, ys) = events(claims);
+    let mut tree = CoverageTree::new(ys, 2);
+    let mut area = 0;
+    let mut last_x = 0;
+    for (x, delta, top, bottom) in events {
+        area += (x - last_x) * tree.covered(2);
+        tree.update(top, bottom, delta);
+        last_x = x;
+    }
+    area
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DepthHistogram {
+    // exactly[k] is the area covered by exactly k claims, for k in 0..=max_depth.
+    // Depth 0 only counts the uncovered area inside the bounding box of all the claims.
+    pub exactly: Vec<usize>,
+    // at_least[k] is the area covered by k or more claims (so at_least[2] is part 1's answer)
+    pub at_least: Vec<usize>,
+    pub max_depth: usize,
+    // the top-left-most square inch (x, y) where max_depth is reached, if there are any claims
+    pub max_location: Option<(usize, usize)>,
+}
+
+// Two passes: first a range-add/max sweep to find the deepest pile-up, then a CoverageTree sweep
+// capped at that depth. That makes it O(n log n * max_depth) rather than O(n log n), which is fine
+// since pile-ups are shallow compared to the number of claims.
+pub fn depth_histogram(claims: &[Claim]) -> DepthHistogram {
+    let (events, ys) = events(claims);
+    let intervals = ys.len().saturating_sub(1);
+
+    let mut max_tree = MaxTree::new(intervals);
+    let mut max_depth = 0;
+    let mut max_location = None;
+    let mut last_x = 0;
+    for &(x, delta, top, bottom) in &events {
+        if x > last_x && intervals > 0 {
+            // the tree currently describes the slab last_x..x
+            let (depth, index) = max_tree.argmax();
+            if depth > max_depth {
+                max_depth = depth;
+                max_location = Some((last_x, ys[index]));
+            }
+        }
+        let lo = ys.binary_search(&top).unwrap();
+        let hi = ys.binary_search(&bottom).unwrap();
+        max_tree.update(1, 0, intervals, lo, hi, delta);
+        last_x = x;
+    }
+
+    let bounding_area = match (events.first(), events.last(), ys.first(), ys.last()) {
+        (Some(first), Some(last), Some(min_y), Some(max_y)) => (last.0 - first.0) * (max_y - min_y),
+        _ => 0,
+    };
+    let mut at_least = vec![0; max_depth + 1];
+    at_least[0] = bounding_area;
+    let mut tree = CoverageTree::new(ys, max_depth);
+    let mut last_x = 0;
+    for (x, delta, top, bottom) in events {
+        for (k, area) in at_least.iter_mut().enumerate().skip(1) {
+            *area += (x - last_x) * tree.covered(k);
+        }
+        tree.update(top, bottom, delta);
+        last_x = x;
+    }
+    let exactly = (0..=max_depth)
+        .map(|k| at_least[k] - at_least.get(k + 1).unwrap_or(&0))
+        .collect();
+    DepthHistogram {
+        exactly,
+        at_least,
+        max_depth,
+        max_location,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_input;
+
+    #[test]
+    fn test_overlap_area() {
+        let claims = parse_input("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2");
+        assert_eq!(4, overlap_area(&claims));
+        let claims = parse_input(
+            "#1 @ 0,0: 3000000x2000000\n#2 @ 1000000,1000000: 4000000x4000000\n#3 @ 0,0: 1x1",
+        );
+        assert_eq!(2_000_000_000_000 + 1, overlap_area(&claims));
+    }
+
+    #[test]
+    fn test_depth_histogram() {
+        let claims = parse_input("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n#4 @ 4,4: 1x1");
+        let histogram = depth_histogram(&claims);
+        assert_eq!(3, histogram.max_depth);
+        assert_eq!(Some((4, 4)), histogram.max_location);
+        assert_eq!(vec![36, 32, 4, 1], histogram.at_least);
+        assert_eq!(vec![4, 28, 3, 1], histogram.exactly);
+        assert_eq!(overlap_area(&claims), histogram.at_least[2]);
+    }
+}
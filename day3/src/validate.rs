@@ -0,0 +1,217 @@
+// The puzzle's parsing trusts its input: a bad line panics, and a duplicate id, an empty claim or a
+// claim hanging off the fabric just gets used.
+// This checks a claim file line by line and hands back the claims that are safe to use.
+
+use crate::{Claim, CLAIM_PATTERN};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    // doesn't look like `#id @ left,top: widthxheight`
+    Unparseable,
+    // a number too big to fit in a usize
+    NumberTooLarge,
+    // left + width or top + height doesn't fit in a usize
+    Overflow,
+    DuplicateId { first_line: usize },
+    ZeroSize,
+    OutOfBounds,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Issue {
+    // 1-based, like an editor
+    pub line: usize,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::Unparseable => write!(f, "not a claim"),
+            Problem::NumberTooLarge => write!(f, "number too large"),
+            Problem::Overflow => write!(f, "claim extends past the largest coordinate"),
+            Problem::DuplicateId { first_line } => {
+                write!(f, "claim id already used on line {}", first_line)
+            }
+            Problem::ZeroSize => write!(f, "claim has zero width or height"),
+            Problem::OutOfBounds => write!(f, "claim extends beyond the fabric"),
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.problem)
+    }
+}
+
+// Parses one claim, checking everything that can be checked without the rest of the file: that it
+// is a claim, that its numbers and far edges fit in a usize, and that it isn't empty. `re` is
+// CLAIM_PATTERN, compiled once by the caller.
+pub fn check_claim(re: &Regex, line: &str) -> Result<Claim, Problem> {
+    let cap = re.captures(line.trim_end()).ok_or(Problem::Unparseable)?;
+    let numbers: Result<Vec<usize>, _> = (1..=5).map(|i| cap[i].parse::<usize>()).collect();
+    let numbers = numbers.map_err(|_| Problem::NumberTooLarge)?;
+    let claim = Claim {
+        id: numbers[0],
+        left: numbers[1],
+        top: numbers[2],
+        width: numbers[3],
+        height: numbers[4],
+    };
+    if claim.left.checked_add(claim.width).is_none()
+        || claim.top.checked_add(claim.height).is_none()
+    {
+        return Err(Problem::Overflow);
+    }
+    if claim.width == 0 || claim.height == 0 {
+        return Err(Problem::ZeroSize);
+    }
+    Ok(claim)
+}
+
+// Returns the usable claims along with every problem found. Lines that can't be parsed, overflow,
+// reuse an earlier id or have zero size are left out; claims sticking out of the width x height
+// fabric are reported and clipped to it (or left out if they're entirely outside).
+pub fn validate(input: &str, width: usize, height: usize) -> (Vec<Claim>, Vec<Issue>) {
+    let re = Regex::new(CLAIM_PATTERN).unwrap();
+    let mut claims = Vec::new();
+    let mut issues = Vec::new();
+    let mut seen_ids: HashMap<usize, usize> = HashMap::new();
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let mut report = |problem| {
+            issues.push(Issue {
+                line: line_number,
+                problem,
+            })
+        };
+        let mut claim = match check_claim(&re, line) {
+            Ok(claim) => claim,
+            Err(problem) => {
+                report(problem);
+                continue;
+            }
+        };
+        if let Some(&first_line) = seen_ids.get(&claim.id) {
+            report(Problem::DuplicateId { first_line });
+            continue;
+        }
+        let (right, bottom) = (claim.right(), claim.bottom());
+        if right > width || bottom > height {
+            report(Problem::OutOfBounds);
+            if claim.left >= width || claim.top >= height {
+                continue;
+            }
+            claim.width = right.min(width) - claim.left;
+            claim.height = bottom.min(height) - claim.top;
+        }
+        // only claims that made it this far take their id, so a rejected line doesn't block a
+        // good one later on
+        seen_ids.insert(claim.id, line_number);
+        claims.push(claim);
+    }
+    (claims, issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let input = "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#1 @ 5,5: 2x2\nhello\n#4 @ 5,5: 0x2\n\
+                     #5 @ 8,8: 4x4\n#6 @ 18446744073709551615,0: 2x2\n#7 @ 99999999999999999999,0: 1x1\n\
+                     #8 @ 20,0: 1x1";
+        let (claims, issues) = validate(input, 10, 10);
+        let ids: Vec<usize> = claims.iter().map(|claim| claim.id).collect();
+        assert_eq!(vec![1, 2, 5], ids);
+        assert_eq!((2, 2), (claims[2].width, claims[2].height));
+        assert_eq!(
+            vec![
+                Issue {
+                    line: 3,
+                    problem: Problem::DuplicateId { first_line: 1 }
+                },
+                Issue {
+                    line: 4,
+                    problem: Problem::Unparseable
+                },
+                Issue {
+                    line: 5,
+                    problem: Problem::ZeroSize
+                },
+                Issue {
+                    line: 6,
+                    problem: Problem::OutOfBounds
+                },
+                Issue {
+                    line: 7,
+                    problem: Problem::Overflow
+                },
+                Issue {
+                    line: 8,
+                    problem: Problem::NumberTooLarge
+                },
+                Issue {
+                    line: 9,
+                    problem: Problem::OutOfBounds
+                },
+            ],
+            issues
+        );
+        assert_eq!(
+            "line 3: claim id already used on line 1",
+            issues[0].to_string()
+        );
+    }
+
+    #[test]
+    fn test_rejected_id_is_reusable() {
+        let input = "#1 @ 0,0: 0x2
+#1 @ 1,1: 2x2
+#1 @ 3,3: 1x1";
+        let (claims, issues) = validate(input, 10, 10);
+        assert_eq!(1, claims.len());
+        assert_eq!((1, 1), (claims[0].left, claims[0].top));
+        assert_eq!(
+            vec![
+                Issue {
+                    line: 1,
+                    problem: Problem::ZeroSize
+                },
+                Issue {
+                    line: 3,
+                    problem: Problem::DuplicateId { first_line: 2 }
+                },
+            ],
+            issues
+        );
+    }
+
+    #[test]
+    fn test_check_claim() {
+        let re = Regex::new(CLAIM_PATTERN).unwrap();
+        assert_eq!(
+            (9, 3),
+            check_claim(&re, "#9 @ 1,2: 3x4")
+                .map(|claim| (claim.id, claim.width))
+                .unwrap()
+        );
+        let problem = |line| check_claim(&re, line).err();
+        assert_eq!(
+            Some(Problem::Overflow),
+            problem("#9 @ 18446744073709551615,0: 2x2")
+        );
+        assert_eq!(
+            Some(Problem::NumberTooLarge),
+            problem("#99999999999999999999 @ 0,0: 2x2")
+        );
+        assert_eq!(Some(Problem::ZeroSize), problem("#9 @ 0,0: 2x0"));
+        assert_eq!(Some(Problem::Unparseable), problem("remove 9"));
+        assert_eq!("number too large", Problem::NumberTooLarge.to_string());
+    }
+}
@@ -0,0 +1,244 @@
+// Picks claims to withdraw so that nothing overlaps any more. The fabric is clean exactly when no
+// two remaining claims overlap, so this is a minimum (weighted) vertex cover of the conflict graph.
+// That's NP-hard in general, so each connected group of conflicting claims is solved exactly by
+// branch and bound when it is small, and otherwise with the local ratio 2-approximation, which also
+// gives us a lower bound on the best possible cost to measure the answer against.
+
+use crate::conflicts::ConflictGraph;
+use crate::Claim;
+
+// Groups of conflicting claims bigger than this use the approximation.
+pub const EXACT_LIMIT: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Objective {
+    // withdraw as few claims as possible
+    Count,
+    // withdraw as little total claimed area as possible
+    Area,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Withdrawal {
+    // ids of the claims to withdraw, ascending
+    pub claims: Vec<usize>,
+    // number or total area of those claims, depending on the objective
+    pub cost: usize,
+    // no valid withdrawal can cost less than this; equal to cost when every group was solved exactly
+    pub lower_bound: usize,
+}
+
+fn weight(claim: &Claim, objective: Objective) -> usize {
+    match objective {
+        Objective::Count => 1,
+        Objective::Area => claim.width * claim.height,
+    }
+}
+
+// Local ratio: for each edge, knock the same amount off both ends' remaining weight. Whatever hits
+// zero is withdrawn. The total knocked off is a lower bound on the optimum, and withdrawing every
+// vertex that hit zero costs at most twice that. Returns (remaining weights, lower bound).
+fn local_ratio(
+    adjacency: &[Vec<usize>],
+    weights: &[usize],
+    present: &[bool],
+) -> (Vec<usize>, usize) {
+    let mut remaining = weights.to_vec();
+    let mut lower_bound = 0;
+    for u in (0..adjacency.len()).filter(|&u| present[u]) {
+        for &v in &adjacency[u] {
+            if u < v && present[v] {
+                let paid = remaining[u].min(remaining[v]);
+                remaining[u] -= paid;
+                remaining[v] -= paid;
+                lower_bound += paid;
+            }
+        }
+    }
+    (remaining, lower_bound)
+}
+
+// Branch on the vertex with the most remaining edges: either withdraw it, or keep it and withdraw
+// all of its neighbours instead. Branches that can't beat the best so far, even with the local
+// ratio lower bound for what's left, are cut off.
+fn branch(
+    adjacency: &[Vec<usize>],
+    weights: &[usize],
+    present: &mut Vec<bool>,
+    chosen: &mut Vec<usize>,
+    cost: usize,
+    best: &mut (usize, Vec<usize>),
+) {
+    if cost + local_ratio(adjacency, weights, present).1 >= best.0 {
+        return;
+    }
+    let degree =
+        |v: usize, present: &Vec<bool>| adjacency[v].iter().filter(|&&u| present[u]).count();
+    let pivot = (0..adjacency.len())
+        .filter(|&v| present[v])
+        .map(|v| (degree(v, present), v))
+        .max();
+    let v = match pivot {
+        Some((d, v)) if d > 0 => v,
+        _ => {
+            *best = (cost, chosen.clone());
+            return;
+        }
+    };
+
+    present[v] = false;
+    chosen.push(v);
+    branch(adjacency, weights, present, chosen, cost + weights[v], best);
+    chosen.pop();
+
+    let neighbours: Vec<usize> = adjacency[v]
+        .iter()
+        .cloned()
+        .filter(|&u| present[u])
+        .collect();
+    let neighbour_cost: usize = neighbours.iter().map(|&u| weights[u]).sum();
+    for &u in &neighbours {
+        present[u] = false;
+        chosen.push(u);
+    }
+    branch(
+        adjacency,
+        weights,
+        present,
+        chosen,
+        cost + neighbour_cost,
+        best,
+    );
+    for &u in &neighbours {
+        present[u] = true;
+        chosen.pop();
+    }
+    present[v] = true;
+}
+
+fn solve_exact(adjacency: &[Vec<usize>], weights: &[usize]) -> Vec<usize> {
+    let mut present = vec![true; adjacency.len()];
+    let mut best = (usize::MAX, Vec::new());
+    branch(
+        adjacency,
+        weights,
+        &mut present,
+        &mut Vec::new(),
+        0,
+        &mut best,
+    );
+    best.1
+}
+
+// The local ratio cover, after putting back anything whose neighbours are all withdrawn anyway.
+fn solve_approximate(adjacency: &[Vec<usize>], weights: &[usize]) -> (Vec<usize>, usize) {
+    let (remaining, lower_bound) = local_ratio(adjacency, weights, &vec![true; adjacency.len()]);
+    let mut withdrawn: Vec<bool> = remaining.iter().map(|&r| r == 0).collect();
+    let mut by_weight: Vec<usize> = (0..adjacency.len()).filter(|&v| withdrawn[v]).collect();
+    by_weight.sort_by_key(|&v| std::cmp::Reverse(weights[v]));
+    for v in by_weight {
+        if adjacency[v].iter().all(|&u| withdrawn[u]) {
+            withdrawn[v] = false;
+        }
+    }
+    let cover = (0..adjacency.len()).filter(|&v| withdrawn[v]).collect();
+    (cover, lower_bound)
+}
+
+// exact_limit is the largest group of conflicting claims solved exactly (0 means always approximate)
+pub fn withdraw(claims: &[Claim], objective: Objective, exact_limit: usize) -> Withdrawal {
+    let graph = ConflictGraph::build(claims);
+    let mut seen = vec![false; claims.len()];
+    let mut withdrawn = Vec::new();
+    let mut cost = 0;
+    let mut lower_bound = 0;
+    // claim index -> position in the group being solved, only meaningful for that group's claims
+    let mut local = vec![0; claims.len()];
+    for start in 0..claims.len() {
+        if seen[start] || graph.degree(start) == 0 {
+            continue;
+        }
+        // collect the connected group, then renumber it 0..n for the solvers
+        let mut group = vec![start];
+        seen[start] = true;
+        let mut next = 0;
+        while next < group.len() {
+            for (other, _) in graph.neighbours(group[next]) {
+                if !seen[other] {
+                    seen[other] = true;
+                    group.push(other);
+                }
+            }
+            next += 1;
+        }
+        for (position, &g) in group.iter().enumerate() {
+            local[g] = position;
+        }
+        let adjacency: Vec<Vec<usize>> = group
+            .iter()
+            .map(|&g| {
+                graph
+                    .neighbours(g)
+                    .into_iter()
+                    .map(|(o, _)| local[o])
+                    .collect()
+            })
+            .collect();
+        let weights: Vec<usize> = group
+            .iter()
+            .map(|&g| weight(&claims[g], objective))
+            .collect();
+        let (cover, bound) = if group.len() <= exact_limit {
+            let cover = solve_exact(&adjacency, &weights);
+            let bound = cover.iter().map(|&v| weights[v]).sum();
+            (cover, bound)
+        } else {
+            solve_approximate(&adjacency, &weights)
+        };
+        cost += cover.iter().map(|&v| weights[v]).sum::<usize>();
+        lower_bound += bound;
+        withdrawn.extend(cover.into_iter().map(|v| claims[group[v]].id));
+    }
+    withdrawn.sort_unstable();
+    Withdrawal {
+        claims: withdrawn,
+        cost,
+        lower_bound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_input;
+
+    // a chain 1 - 2 - 3 - 4 where claim 2 and 3 are big, plus an untouched claim 5
+    const TEST_INPUT: &str =
+        "#1 @ 0,0: 2x2\n#2 @ 1,1: 5x5\n#3 @ 5,5: 5x5\n#4 @ 9,9: 3x3\n#5 @ 20,20: 1x1";
+
+    #[test]
+    fn test_withdraw_exact() {
+        let claims = parse_input(TEST_INPUT);
+        let by_count = withdraw(&claims, Objective::Count, EXACT_LIMIT);
+        assert_eq!(2, by_count.cost);
+        assert_eq!(2, by_count.lower_bound);
+        let by_area = withdraw(&claims, Objective::Area, EXACT_LIMIT);
+        assert_eq!(vec![1, 3], by_area.claims);
+        assert_eq!(29, by_area.cost);
+        assert_eq!(29, by_area.lower_bound);
+    }
+
+    #[test]
+    fn test_withdraw_approximate() {
+        let claims = parse_input(TEST_INPUT);
+        for objective in [Objective::Count, Objective::Area].iter() {
+            let result = withdraw(&claims, *objective, 0);
+            assert!(result.lower_bound <= result.cost && result.cost <= 2 * result.lower_bound);
+            let kept: Vec<Claim> = parse_input(TEST_INPUT)
+                .into_iter()
+                .filter(|claim| !result.claims.contains(&claim.id))
+                .collect();
+            assert!(ConflictGraph::build(&kept).conflicts.is_empty());
+        }
+    }
+}
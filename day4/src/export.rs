@@ -0,0 +1,124 @@
+// Every nap as a flat (guard, shift date, sleep start, sleep end) interval for our scheduling
+// tools: CSV, JSON, or an iCalendar file with one event per nap. The end is when the guard woke
+// up, so the guard was asleep up to but not including it.
+
+use crate::log::Timestamp;
+use crate::query::json_string;
+use crate::sleep::{Nap, Shift};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+    Ical,
+}
+
+impl Format {
+    pub fn from_path(path: &str) -> Option<Format> {
+        let extension = path.rsplit('.').next()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            "ics" => Some(Format::Ical),
+            _ => None,
+        }
+    }
+}
+
+fn naps(shifts: &[Shift]) -> impl Iterator<Item = (&Shift, &Nap)> {
+    shifts
+        .iter()
+        .flat_map(|shift| shift.naps.iter().map(move |nap| (shift, nap)))
+}
+
+// 1518-11-01 00:05 as 15181101T000500, a "floating" local time since the log has no time zone
+fn ical_time(time: Timestamp) -> String {
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}00",
+        time.date.year, time.date.month, time.date.day, time.hour, time.minute
+    )
+}
+
+pub fn write(shifts: &[Shift], format: Format) -> String {
+    match format {
+        Format::Csv => {
+            let mut out = "guard,shift_date,start,end\n".to_string();
+            for (shift, nap) in naps(shifts) {
+                out.push_str(&format!(
+                    "{},{},{},{}\n",
+                    nap.guard, shift.date, nap.start, nap.end
+                ));
+            }
+            out
+        }
+        Format::Json => {
+            let objects: Vec<String> = naps(shifts)
+                .map(|(shift, nap)| {
+                    format!(
+                        "  {{\"guard\": {}, \"shift_date\": {}, \"start\": {}, \"end\": {}}}",
+                        nap.guard,
+                        json_string(&shift.date.to_string()),
+                        json_string(&nap.start.to_string()),
+                        json_string(&nap.end.to_string())
+                    )
+                })
+                .collect();
+            if objects.is_empty() {
+                "[]\n".to_string()
+            } else {
+                format!("[\n{}\n]\n", objects.join(",\n"))
+            }
+        }
+        Format::Ical => {
+            // RFC 5545 wants CRLF line endings
+            let mut lines = vec![
+                "BEGIN:VCALENDAR".to_string(),
+                "VERSION:2.0".to_string(),
+                "PRODID:-//advent-of-code-2018//day4 guard naps//EN".to_string(),
+            ];
+            for (shift, nap) in naps(shifts) {
+                let start = ical_time(nap.start);
+                lines.extend(vec![
+                    "BEGIN:VEVENT".to_string(),
+                    format!("UID:guard-{}-{}@day4", nap.guard, start),
+                    // the log doesn't say when it was written, so the nap itself stands in
+                    // (marked as UTC, which DTSTAMP has to be)
+                    format!("DTSTAMP:{}Z", start),
+                    format!("DTSTART:{}", start),
+                    format!("DTEND:{}", ical_time(nap.end)),
+                    format!("SUMMARY:Guard #{} asleep", nap.guard),
+                    format!("DESCRIPTION:Shift of {}", shift.date),
+                    "END:VEVENT".to_string(),
+                ]);
+            }
+            lines.push("END:VCALENDAR".to_string());
+            lines.join("\r\n") + "\r\n"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::read_events;
+    use crate::sleep::shifts;
+
+    #[test]
+    fn test_write() {
+        let input = "[1518-11-01 23:58] Guard #99 begins shift\n[1518-11-01 23:59] falls asleep\n[1518-11-02 00:50] wakes up";
+        let shifts = shifts(&read_events(input).unwrap());
+        assert_eq!(
+            "guard,shift_date,start,end\n99,1518-11-02,1518-11-01 23:59,1518-11-02 00:50\n",
+            write(&shifts, Format::Csv)
+        );
+        assert_eq!(
+            "[\n  {\"guard\": 99, \"shift_date\": \"1518-11-02\", \"start\": \"1518-11-01 23:59\", \"end\": \"1518-11-02 00:50\"}\n]\n",
+            write(&shifts, Format::Json)
+        );
+        let ical = write(&shifts, Format::Ical);
+        assert!(ical.contains("\r\nDTSTART:15181101T235900\r\nDTEND:15181102T005000\r\n"));
+        assert!(ical.starts_with("BEGIN:VCALENDAR\r\n") && ical.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!("[]\n", write(&[], Format::Json));
+        assert_eq!(Some(Format::Ical), Format::from_path("naps.ICS"));
+    }
+}
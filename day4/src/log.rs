@@ -0,0 +1,373 @@
+// The guard log as real events with real timestamps, rather than lines sorted as strings with
+// only the minute pulled out. Everything else in day 4 is built from these.
+
+use crate::GuardId;
+use regex::Regex;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    // Days since 0000-03-01 in the proleptic Gregorian calendar (Howard Hinnant's days_from_civil
+    // with the epoch moved back). Only differences between these mean anything.
+    pub fn to_days(self) -> i64 {
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i64;
+        let month_from_march = if month > 2 { month - 3 } else { month + 9 };
+        let day_of_year = (153 * month_from_march + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era
+    }
+
+    pub fn from_days(days: i64) -> Date {
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+        let month = if month_from_march < 10 {
+            month_from_march + 3
+        } else {
+            month_from_march - 9
+        } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Date { year, month, day }
+    }
+
+    fn days_in_month(year: i64, month: u32) -> u32 {
+        match month {
+            2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Date> {
+        let mut parts = text.splitn(3, '-');
+        let year = parts.next()?.parse::<i64>().ok()?;
+        let month = parts.next()?.parse::<u32>().ok()?;
+        let day = parts.next()?.parse::<u32>().ok()?;
+        if month == 0 || month > 12 || day == 0 || day > Date::days_in_month(year, month) {
+            return None;
+        }
+        Some(Date { year, month, day })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub date: Date,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl Timestamp {
+    // Parses `1518-05-19 23:50`.
+    pub fn parse(text: &str) -> Option<Timestamp> {
+        let (date, time) = text.split_at(text.find(' ')?);
+        let mut time = time[1..].splitn(2, ':');
+        let hour = time.next()?.parse::<u32>().ok()?;
+        let minute = time.next()?.parse::<u32>().ok()?;
+        if hour > 23 || minute > 59 {
+            return None;
+        }
+        Some(Timestamp {
+            date: Date::parse(date)?,
+            hour,
+            minute,
+        })
+    }
+
+    // Minutes since the same (arbitrary) epoch as Date::to_days.
+    pub fn to_minutes(self) -> i64 {
+        self.date.to_days() * 1440 + (self.hour * 60 + self.minute) as i64
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:02}:{:02}", self.date, self.hour, self.minute)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    BeginsShift(GuardId),
+    FallsAsleep,
+    WakesUp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Event {
+    pub time: Timestamp,
+    pub kind: EventKind,
+    // 1-based line in the input it came from
+    pub line: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    // not one of the three kinds of log line, or a date/time that doesn't exist
+    Unrecognised,
+    // another event has exactly the same timestamp
+    DuplicateTimestamp { other_line: usize },
+    // falls asleep or wakes up before any guard has begun a shift
+    BeforeAnyShift,
+    // falls asleep while already asleep
+    AlreadyAsleep { since_line: usize },
+    // wakes up without having fallen asleep
+    NotAsleep,
+    // a new shift begins (or the log ends) while the guard is still asleep
+    NeverWokeUp { since_line: usize },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Issue {
+    pub line: usize,
+    pub problem: Problem,
+}
+
+impl Issue {
+    // The problem with every line number named by `place`, so a merged log can point back into the
+    // files it came from.
+    pub fn describe(&self, place: &dyn Fn(usize) -> String) -> String {
+        let problem = match self.problem {
+            Problem::Unrecognised => "unrecognised log line".to_string(),
+            Problem::DuplicateTimestamp { other_line } => {
+                format!("same timestamp as {}", place(other_line))
+            }
+            Problem::BeforeAnyShift => "event before any guard began a shift".to_string(),
+            Problem::AlreadyAsleep { since_line } => {
+                format!(
+                    "falls asleep while already asleep since {}",
+                    place(since_line)
+                )
+            }
+            Problem::NotAsleep => "wakes up without falling asleep".to_string(),
+            Problem::NeverWokeUp { since_line } => {
+                format!("guard asleep since {} never wakes up", place(since_line))
+            }
+        };
+        format!("{}: {}", place(self.line), problem)
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.describe(&|line| format!("line {}", line)))
+    }
+}
+
+pub fn parse_line(regex: &Regex, line: &str, line_number: usize) -> Option<Event> {
+    let cap = regex.captures(line.trim_end())?;
+    let time = Timestamp::parse(&cap["time"])?;
+    let kind = if let Some(id) = cap.name("guard_id") {
+        EventKind::BeginsShift(id.as_str().parse::<GuardId>().ok()?)
+    } else if cap.name("asleep").is_some() {
+        EventKind::FallsAsleep
+    } else {
+        EventKind::WakesUp
+    };
+    Some(Event {
+        time,
+        kind,
+        line: line_number,
+    })
+}
+
+pub fn line_regex() -> Regex {
+    // [1518-05-19 23:50] Guard #2447 begins shift
+    // [1518-10-11 00:33] falls asleep
+    // [1518-09-26 00:18] wakes up
+    Regex::new(
+        r"^\[(?P<time>\d{4}-\d{2}-\d{2} \d{2}:\d{2})\] (Guard #(?P<guard_id>\d+) begins shift|(?P<asleep>falls asleep)|wakes up)$",
+    )
+    .unwrap()
+}
+
+// Parses every line, in time order (ties in line order). Blank lines are skipped, anything else
+// that doesn't parse is reported.
+fn parse_events(input: &str) -> (Vec<Event>, Vec<Issue>) {
+    let regex = line_regex();
+    let mut events = Vec::new();
+    let mut issues = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(&regex, line, index + 1) {
+            Some(event) => events.push(event),
+            None => issues.push(Issue {
+                line: index + 1,
+                problem: Problem::Unrecognised,
+            }),
+        }
+    }
+    events.sort_by_key(|event| (event.time, event.line));
+    (events, issues)
+}
+
+// Walks the sorted events checking each guard goes shift -> asleep -> awake -> asleep ... and
+// that no two events share a timestamp.
+fn check_sequence(events: &[Event]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut on_shift = false;
+    let mut asleep_since: Option<usize> = None;
+    for (i, event) in events.iter().enumerate() {
+        let mut report = |problem| {
+            issues.push(Issue {
+                line: event.line,
+                problem,
+            })
+        };
+        if i > 0 && events[i - 1].time == event.time {
+            report(Problem::DuplicateTimestamp {
+                other_line: events[i - 1].line,
+            });
+        }
+        match event.kind {
+            EventKind::BeginsShift(_) => {
+                if let Some(since_line) = asleep_since.take() {
+                    report(Problem::NeverWokeUp { since_line });
+                }
+                on_shift = true;
+            }
+            EventKind::FallsAsleep if !on_shift => report(Problem::BeforeAnyShift),
+            EventKind::FallsAsleep => match asleep_since {
+                Some(since_line) => report(Problem::AlreadyAsleep { since_line }),
+                None => asleep_since = Some(event.line),
+            },
+            EventKind::WakesUp if !on_shift => report(Problem::BeforeAnyShift),
+            EventKind::WakesUp => {
+                if asleep_since.take().is_none() {
+                    report(Problem::NotAsleep);
+                }
+            }
+        }
+    }
+    if let (Some(since_line), Some(last)) = (asleep_since, events.last()) {
+        issues.push(Issue {
+            line: last.line,
+            problem: Problem::NeverWokeUp { since_line },
+        });
+    }
+    issues
+}
+
+// Parses and checks the whole log: every event that parsed, along with every problem found in
+// line order.
+pub fn check(input: &str) -> (Vec<Event>, Vec<Issue>) {
+    let (events, mut issues) = parse_events(input);
+    issues.extend(check_sequence(&events));
+    issues.sort_by_key(|issue| issue.line);
+    (events, issues)
+}
+
+// The sorted events, but only if the log had no problems at all. main goes through check so it
+// can say where problems in a merged log came from, so this is for the tests.
+#[cfg(test)]
+pub fn read_events(input: &str) -> Result<Vec<Event>, Vec<Issue>> {
+    let (events, issues) = check(input);
+    if issues.is_empty() {
+        Ok(events)
+    } else {
+        Err(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        let time = Timestamp::parse("1518-02-28 23:58").unwrap();
+        assert_eq!("1518-02-28 23:58", time.to_string());
+        assert!(time < Timestamp::parse("1518-03-01 00:00").unwrap());
+        assert_eq!(
+            2,
+            Timestamp::parse("1518-03-01 00:00").unwrap().to_minutes() - time.to_minutes()
+        );
+        assert_eq!(None, Timestamp::parse("1518-02-29 00:00"));
+        assert_eq!(None, Timestamp::parse("1518-11-01 24:00"));
+        for days in 0..1000 {
+            let date = Date::from_days(Date::parse("1518-01-01").unwrap().to_days() + days);
+            assert_eq!(Some(date), Date::parse(&date.to_string()));
+        }
+        assert_eq!(
+            Some(Date {
+                year: 1600,
+                month: 2,
+                day: 29
+            }),
+            Date::parse("1600-02-29")
+        );
+    }
+
+    #[test]
+    fn test_read_events() {
+        let input = "[1518-11-01 00:05] falls asleep\n\
+                     [1518-11-01 00:00] Guard #10 begins shift\n\
+                     [1518-11-01 00:25] wakes up\n\
+                     [1518-11-01 00:30] wakes up\n\
+                     [1518-11-01 00:40] falls asleep\n\
+                     [1518-11-01 00:45] falls asleep\n\
+                     [1518-11-01 00:45] wakes up\n\
+                     [1518-11-01 00:50] naps\n\
+                     [1518-11-02 00:02] falls asleep";
+        let (events, _) = parse_events(input);
+        assert_eq!(EventKind::BeginsShift(10), events[0].kind);
+        assert_eq!(
+            Err(vec![
+                Issue {
+                    line: 4,
+                    problem: Problem::NotAsleep
+                },
+                Issue {
+                    line: 6,
+                    problem: Problem::AlreadyAsleep { since_line: 5 }
+                },
+                Issue {
+                    line: 7,
+                    problem: Problem::DuplicateTimestamp { other_line: 6 }
+                },
+                Issue {
+                    line: 8,
+                    problem: Problem::Unrecognised
+                },
+                Issue {
+                    line: 9,
+                    problem: Problem::NeverWokeUp { since_line: 9 }
+                },
+            ]),
+            read_events(input)
+        );
+        assert_eq!(
+            vec![Issue {
+                line: 1,
+                problem: Problem::BeforeAnyShift
+            }],
+            check_sequence(&parse_events("[1518-11-01 00:05] wakes up").0)
+        );
+    }
+}
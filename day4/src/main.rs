@@ -1,129 +1,491 @@
-use regex::Regex;
-use std::collections::HashMap;
+mod export;
+mod log;
+mod merge;
+mod predict;
+mod query;
+mod rank;
+mod sleep;
+mod stats;
+mod strategy;
+mod timeline;
+
+use log::Event;
+use merge::{MergeReport, Source};
+use rank::TieBreak;
+use sleep::{Shift, Window};
+use stats::Stats;
+use std::env;
 use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::process;
+use strategy::{Answer, MostAsleep, MostFrequentMinute, Strategy};
 
 type GuardId = u32;
 type Minute = u32;
 
-fn parse_log(input: &str) -> HashMap<GuardId, HashMap<Minute, u32>> {
-    let date_regex: &str = r"^\[(?P<date>\d{4}-\d{2}-\d{2}) \d{2}:(?P<minute>\d{2})\]";
-    // [1518-05-19 23:50] Guard #2447 begins shift
-    let begin_shift_regex: Regex = Regex::new(&format!(
-        "{}{}",
-        date_regex, r" Guard #(?P<guard_id>\d+) begins shift$"
-    ))
-    .unwrap();
-    // [1518-10-11 00:33] falls asleep
-    let falls_asleep_regex: Regex =
-        Regex::new(&format!("{}{}", date_regex, r" falls asleep$")).unwrap();
-    // [1518-09-26 00:18] wakes up
-    let wakes_up_regex: Regex = Regex::new(&format!("{}{}", date_regex, r" wakes up$")).unwrap();
-
-    // step 1: sort input for easier parsing
-    let mut lines: Vec<&str> = input.lines().collect();
-    lines.sort();
-
-    let mut record: HashMap<GuardId, HashMap<Minute, u32>> = HashMap::new();
-    let mut guard_id: GuardId = 0;
-    let mut sleep_minute: Minute = 0;
-    for line in lines {
-        // NB: This logic assumes valid input, that there is always sleep first/wake up second, after guard ID
-        // step 2: parse each guard ID, then their awake/asleep times
-        if let Some(cap) = begin_shift_regex.captures(line) {
-            guard_id = cap["guard_id"].parse::<GuardId>().unwrap();
-        } else if let Some(cap) = falls_asleep_regex.captures(line) {
-            sleep_minute = cap["minute"].parse::<Minute>().unwrap();
-        } else if let Some(cap) = wakes_up_regex.captures(line) {
-            let wake_minute = cap["minute"].parse::<Minute>().unwrap();
-            // step 3: add to global record of each guards sleep minutes HashMap<GuardId, HashMap<Minute, u32>>
-            for minute in sleep_minute..wake_minute {
-                *record
-                    .entry(guard_id)
-                    .or_insert_with(HashMap::new)
-                    .entry(minute)
-                    .or_insert(0) += 1;
+// The log to analyse. When it was merged from --input files, the merge report knows which file and
+// line each of its lines came from, and which lines it had to leave out.
+struct Input {
+    text: String,
+    merge: Option<MergeReport>,
+}
+
+impl Input {
+    fn puzzle(text: &str) -> Input {
+        Input {
+            text: text.to_string(),
+            merge: None,
+        }
+    }
+
+    // `line 12` of the puzzle input, or `north.txt:12` for a merged log
+    fn place(&self, line: usize) -> String {
+        match &self.merge {
+            Some(report) => {
+                let (source, line) = report.origins[line - 1];
+                report.place(source, line)
             }
+            None => format!("line {}", line),
         }
     }
-    record
+
+    // Every event, and every problem with the log, including lines the merge left out.
+    fn check(&self) -> (Vec<Event>, Vec<String>) {
+        let (events, issues) = log::check(&self.text);
+        let mut problems = Vec::new();
+        if let Some(report) = &self.merge {
+            for (source, issue) in &report.unrecognised {
+                problems.push(issue.describe(&|line| report.place(*source, line)));
+            }
+        }
+        problems.extend(
+            issues
+                .iter()
+                .map(|issue| issue.describe(&|line| self.place(line))),
+        );
+        (events, problems)
+    }
 }
 
-fn part1(input: &str) -> (GuardId, Minute) {
-    let record = parse_log(input);
-    // step 4: find guard with most minutes asleep
-    let sleepiest_guard: GuardId = record
-        .iter()
-        .map(|(guard_id, minute_counts)| (*guard_id, minute_counts.values().sum::<u32>()))
-        .max_by(|a, b| a.1.cmp(&b.1))
-        .unwrap()
-        .0;
-    // step 5: get sleepiest minute for that guard
-    let sleepiest_minute = *record
-        .get(&sleepiest_guard)
-        .unwrap()
-        .iter()
-        .max_by(|a, b| a.1.cmp(&b.1))
-        .unwrap()
-        .0;
+fn read_shifts(input: &Input) -> Vec<Shift> {
+    // step 1: parse and sort the events, refusing logs that don't make sense rather than guessing
+    let (events, problems) = input.check();
+    if !problems.is_empty() {
+        panic!("invalid guard log:\n{}", problems.join("\n"));
+    }
+    // step 2: split into shifts, pairing up each guard's fall asleep/wake up times
+    sleep::shifts(&events)
+}
+
+fn parse_log(input: &Input, window: Window) -> Stats {
+    // step 3: add up each guard's shifts, naps and the minutes they were asleep in
+    stats::guard_stats(&read_shifts(input), window)
+}
+
+// The example log from the puzzle, which the tests in every module share, with its shifts and its
+// midnight hour stats so they don't each have to parse it themselves.
+#[cfg(test)]
+const TEST_INPUT: &str = "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] falls asleep\n[1518-11-01 00:25] wakes up\n[1518-11-01 00:30] falls asleep\n[1518-11-01 00:55] wakes up\n[1518-11-01 23:58] Guard #99 begins shift\n[1518-11-02 00:40] falls asleep\n[1518-11-02 00:50] wakes up\n[1518-11-03 00:05] Guard #10 begins shift\n[1518-11-03 00:24] falls asleep\n[1518-11-03 00:29] wakes up\n[1518-11-04 00:02] Guard #99 begins shift\n[1518-11-04 00:36] falls asleep\n[1518-11-04 00:46] wakes up\n[1518-11-05 00:03] Guard #99 begins shift\n[1518-11-05 00:45] falls asleep\n[1518-11-05 00:55] wakes up";
+
+#[cfg(test)]
+fn test_shifts() -> Vec<Shift> {
+    read_shifts(&Input::puzzle(TEST_INPUT))
+}
+
+#[cfg(test)]
+fn test_stats() -> Stats {
+    parse_log(&Input::puzzle(TEST_INPUT), Window::MidnightHour)
+}
+
+// " (tied with #99 at minute 45)" when other answers scored the same, so it's clear the answer
+// came from the tie break (only the first few are named)
+fn describe_ties(answer: &Answer) -> String {
+    if answer.tied_with.is_empty() {
+        String::new()
+    } else {
+        let mut others: Vec<String> = answer
+            .tied_with
+            .iter()
+            .take(5)
+            .map(|(guard, minute)| format!("#{} at minute {}", guard, minute))
+            .collect();
+        if answer.tied_with.len() > others.len() {
+            others.push(format!("{} more", answer.tied_with.len() - others.len()));
+        }
+        format!(" (tied with {})", others.join(", "))
+    }
+}
+
+// The number the puzzle wants, guard ID times minute. Guard IDs can be anything up to u32::MAX, so
+// this is worked out in u64 where the product can't overflow.
+fn result(guard: GuardId, minute: Minute) -> u64 {
+    u64::from(guard) * u64::from(minute)
+}
+
+// Both parts are None when nobody slept inside the watch window, which guards who only nap
+// outside it can't change.
+fn part1(stats: &Stats, ties: TieBreak) -> Option<(GuardId, Minute)> {
+    // step 4: find guard with most minutes asleep, and step 5: their sleepiest minute
+    let answer = MostAsleep.choose(stats, ties)?;
     println!(
-        "guard {} slept the most overall, and they slept most at minute {}",
-        sleepiest_guard, sleepiest_minute
+        "guard {} slept the most overall, and they slept most at minute {}{}",
+        answer.guard,
+        answer.minute,
+        describe_ties(&answer)
     );
-    (sleepiest_guard, sleepiest_minute)
+    Some((answer.guard, answer.minute))
 }
 
-fn part2(input: &str) -> (GuardId, Minute) {
-    let record = parse_log(input);
+fn part2(stats: &Stats, ties: TieBreak) -> Option<(GuardId, Minute)> {
     // step 4: find minute that was slept on the most
-    let (guard_id, minute) = record
-        .iter()
-        .map(|(guard_id, minute_counts)| {
-            (
-                *guard_id,
-                minute_counts.iter().max_by(|a, b| a.1.cmp(&b.1)).unwrap(),
-            )
-        })
-        .max_by(|a, b| a.1.cmp(&b.1))
-        .unwrap();
+    let answer = MostFrequentMinute.choose(stats, ties)?;
     println!(
-        "{} slept {} times at minute {}",
-        guard_id, minute.1, minute.0
+        "{} slept {} times at minute {}{}",
+        answer.guard,
+        answer.score,
+        answer.minute,
+        describe_ties(&answer)
     );
-    (guard_id, *minute.0)
+    Some((answer.guard, answer.minute))
 }
 
-fn main() {
-    let input =
-        fs::read_to_string("input/day4.txt").expect("Something went wrong reading the file");
-    let (sleepiest_guard, sleepiest_minute) = part1(&input);
-    println!(
-        "Day 4 Part 1: guard {} minute {} result {}",
-        sleepiest_guard,
-        sleepiest_minute,
-        sleepiest_guard * sleepiest_minute
-    );
-    let (sleepiest_guard, sleepiest_minute) = part2(&input);
-    println!(
-        "Day 4 Part 2: guard {} minute {} result {}",
-        sleepiest_guard,
-        sleepiest_minute,
-        sleepiest_guard * sleepiest_minute
+fn open_sources(paths: &[String]) -> Vec<Source> {
+    paths
+        .iter()
+        .map(|path| Source::open(path).unwrap_or_else(|e| panic!("{}: {}", path, e)))
+        .collect()
+}
+
+// Notes on a merge for stderr, since the merged log may be going to stdout.
+fn report_merge(report: &MergeReport) {
+    for conflict in &report.conflicts {
+        eprintln!("{}", conflict);
+    }
+    eprintln!(
+        "merged {} events, dropped {} duplicates, {} conflicts",
+        report.events,
+        report.duplicates,
+        report.conflicts.len()
     );
 }
 
+fn usage(expected: &str) -> ! {
+    eprintln!("usage: {}", expected);
+    process::exit(2);
+}
+
+// Takes `flag` and the value after it out of the arguments, so they can go anywhere. None if the
+// flag isn't there, and a usage error if the value is missing or `parse` rejects it.
+fn take_flag<T>(
+    args: &mut Vec<String>,
+    flag: &str,
+    expected: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Option<T> {
+    let i = args.iter().position(|arg| arg == flag)?;
+    args.remove(i);
+    match args.get(i).and_then(|value| parse(value)) {
+        Some(value) => {
+            args.remove(i);
+            Some(value)
+        }
+        None => usage(&format!("{} {}", flag, expected)),
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // `--chunk 50000` limits how many events a merge sorts in memory at once
+    let chunk_lines = take_flag(&mut args, "--chunk", "<number of lines>", |value| {
+        value.parse().ok()
+    })
+    .unwrap_or(merge::DEFAULT_CHUNK_LINES);
+    // `--window day` counts sleep by minute of the whole day rather than just the midnight hour
+    let window = take_flag(&mut args, "--window", "midnight|day", Window::from_name)
+        .unwrap_or(Window::MidnightHour);
+    // `--ties highest` settles ties for the highest guard id and latest minute instead of the lowest
+    let ties =
+        take_flag(&mut args, "--ties", "lowest|highest", TieBreak::from_name).unwrap_or_default();
+    // `cargo run -- merge north.txt south.txt - > all.txt` combines logs (`-` is stdin) into one
+    if args.first().map(String::as_str) == Some("merge") {
+        let stdout = io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        let report = merge::merge(open_sources(&args[1..]), chunk_lines, &mut out)
+            .expect("could not merge logs");
+        out.flush().expect("could not write merged log");
+        for (source, issue) in &report.unrecognised {
+            let problem = issue.describe(&|line| report.place(*source, line));
+            eprintln!("{}, left out", problem);
+        }
+        report_merge(&report);
+        return;
+    }
+    // `--input north.txt --input south.txt` analyses those logs, merged, instead of the puzzle input
+    let mut paths = Vec::new();
+    while let Some(i) = args.iter().position(|arg| arg == "--input") {
+        args.remove(i);
+        if i == args.len() {
+            usage("--input <path>");
+        }
+        paths.push(args.remove(i));
+    }
+    let input = if paths.is_empty() {
+        Input::puzzle(
+            &fs::read_to_string("input/day4.txt").expect("Something went wrong reading the file"),
+        )
+    } else {
+        let mut merged = Vec::new();
+        let report = merge::merge_with_origins(open_sources(&paths), chunk_lines, &mut merged)
+            .expect("could not merge logs");
+        report_merge(&report);
+        Input {
+            text: String::from_utf8(merged).expect("merged log should be text"),
+            merge: Some(report),
+        }
+    };
+    // `cargo run -- validate` lists every problem in the log, by line, instead of stopping at them
+    if args.first().map(String::as_str) == Some("validate") {
+        let (events, problems) = input.check();
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        println!("{} events, {} problems", events.len(), problems.len());
+        if !problems.is_empty() {
+            process::exit(1);
+        }
+        return;
+    }
+    // `cargo run -- timeline` prints the night-by-night table, `timeline shifts.html` (or .svg) draws it
+    if args.first().map(String::as_str) == Some("timeline") {
+        let shifts = read_shifts(&input);
+        match args.get(1) {
+            Some(path) if path.ends_with(".svg") => {
+                fs::write(path, timeline::svg(&shifts)).expect("could not write timeline")
+            }
+            Some(path) => {
+                fs::write(path, timeline::html(&shifts)).expect("could not write timeline")
+            }
+            None => print!("{}", timeline::table(&shifts)),
+        }
+        return;
+    }
+    // `cargo run -- export naps.ics` writes every nap out as an interval (csv, json or ics by extension)
+    if args.first().map(String::as_str) == Some("export") {
+        let path = args.get(1).expect("missing output path");
+        let format =
+            export::Format::from_path(path).expect("output should end in .csv, .json or .ics");
+        fs::write(path, export::write(&read_shifts(&input), format)).expect("could not write naps");
+        return;
+    }
+    // `cargo run -- query asleep at 1518-05-19 00:37` answers a question about the log (see query.rs),
+    // as JSON with `--json`
+    if args.first().map(String::as_str) == Some("query") {
+        let json = args.iter().any(|arg| arg == "--json");
+        let text: Vec<&str> = args[1..]
+            .iter()
+            .map(String::as_str)
+            .filter(|&arg| arg != "--json")
+            .collect();
+        let query = query::parse(&text.join(" ")).unwrap_or_else(|e| panic!("{}", e));
+        let table = query::run(&query, &read_shifts(&input));
+        if json {
+            print!("{}", table.to_json());
+        } else {
+            print!("{}", table.to_text());
+        }
+        return;
+    }
+    let stats = parse_log(&input, window);
+    // `cargo run -- stats` summarises every guard, `stats 2447` shows how likely that guard is to be
+    // asleep in each minute
+    if args.first().map(String::as_str) == Some("stats") {
+        match args.get(1).and_then(|id| id.parse::<GuardId>().ok()) {
+            Some(guard) => {
+                let guard_stats = stats.get(&guard).expect("no shifts for that guard");
+                let mut minutes: Vec<&Minute> = guard_stats.histogram.keys().collect();
+                minutes.sort_unstable();
+                println!("minute  nights asleep  probability");
+                for &minute in minutes {
+                    println!(
+                        "{:<6}  {:<13}  {:.3}",
+                        minute,
+                        guard_stats.histogram[&minute],
+                        guard_stats.probability_asleep(minute)
+                    );
+                }
+            }
+            None => {
+                // "in window" counts only the watch window (the midnight hour unless --window day),
+                // "whole shift" and the longest nap count every minute asleep on shift
+                println!(
+                    "guard  shifts  in window  average  whole shift  average  longest nap  first shift  last shift"
+                );
+                for (guard, s) in &stats {
+                    println!(
+                        "{:<5}  {:<6}  {:<9}  {:<7.1}  {:<11}  {:<7.1}  {:<11}  {}   {}",
+                        guard,
+                        s.shifts,
+                        s.minutes_asleep,
+                        s.average_asleep(),
+                        s.total_asleep,
+                        s.average_total_asleep(),
+                        s.longest_nap,
+                        s.first_shift,
+                        s.last_shift
+                    );
+                }
+            }
+        }
+        return;
+    }
+    // `cargo run -- predict 2447` shows the minutes that guard is most likely asleep next shift,
+    // `predict 2447 1487` the best minute to sneak in with both on duty, and `predict backtest 0.7`
+    // trains on the first 70% of the shifts and scores the predictions on the rest
+    if args.first().map(String::as_str) == Some("predict") {
+        let shifts = read_shifts(&input);
+        if args.get(1).map(String::as_str) == Some("backtest") {
+            let fraction = args.get(2).and_then(|f| f.parse().ok()).unwrap_or(0.7);
+            let result = predict::backtest(&shifts, window, fraction);
+            println!(
+                "trained on {} shifts, scored {} ({} by unknown guards skipped)",
+                result.training_shifts, result.scored_shifts, result.unscored_shifts
+            );
+            println!("Brier score {:.4}", result.brier_score);
+            println!(
+                "top minute right {:.1}% of the time",
+                100.0 * result.top_minute_hit_rate
+            );
+            println!("interval coverage {:.1}%", 100.0 * result.interval_coverage);
+            return;
+        }
+        let guards: Vec<GuardId> = args[1..]
+            .iter()
+            .filter_map(|id| id.trim_start_matches('#').parse().ok())
+            .collect();
+        let model = predict::Model::train(&shifts, window);
+        match guards.as_slice() {
+            [] => panic!("usage: predict <guard> [<guard>...] or predict backtest [fraction]"),
+            [guard] => {
+                let predictions = model.predict(*guard).expect("no shifts for that guard");
+                println!("minute  chance asleep  95% interval");
+                for p in predictions.iter().take(10) {
+                    println!(
+                        "{:<6}  {:<13.3}  {:.3}-{:.3}",
+                        p.minute, p.probability, p.low, p.high
+                    );
+                }
+            }
+            _ => match model.best_minute(&guards) {
+                Ok((minute, chance)) => {
+                    println!("minute {}: all asleep with chance {:.3}", minute, chance)
+                }
+                Err(guard) => panic!("no shifts for guard {}", guard),
+            },
+        }
+        return;
+    }
+    // `cargo run -- strategy longest-nap` answers with any of the built-in strategies (`strategy`
+    // on its own lists them)
+    if args.first().map(String::as_str) == Some("strategy") {
+        match args.get(1) {
+            Some(name) => {
+                let strategy = strategy::by_name(name).unwrap_or_else(|| {
+                    panic!("no strategy called {} (try `strategy` for a list)", name)
+                });
+                let answer = match strategy.choose(&stats, ties) {
+                    Some(answer) => answer,
+                    None => {
+                        println!(
+                            "Day 4 {}: nobody slept in the watch window",
+                            strategy.name()
+                        );
+                        return;
+                    }
+                };
+                println!(
+                    "Day 4 {}: guard {} minute {} score {} ({}){} result {}",
+                    strategy.name(),
+                    answer.guard,
+                    answer.minute,
+                    answer.score,
+                    strategy.score_meaning(),
+                    describe_ties(&answer),
+                    result(answer.guard, answer.minute)
+                );
+            }
+            None => {
+                for strategy in strategy::BUILT_IN.iter() {
+                    println!(
+                        "{:<22}  score is {}",
+                        strategy.name(),
+                        strategy.score_meaning()
+                    );
+                }
+            }
+        }
+        return;
+    }
+    // `cargo run -- rank 5` lists the 5 sleepiest guards and the 5 most slept (guard, minute) pairs
+    if args.first().map(String::as_str) == Some("rank") {
+        let n = args.get(1).and_then(|n| n.parse().ok()).unwrap_or(10);
+        println!("guard  minutes asleep");
+        for (guard, total) in rank::top_guards(&stats, n, ties) {
+            println!("{:<5}  {}", guard, total);
+        }
+        println!("guard  minute  times asleep");
+        for ((guard, minute), count) in rank::top_pairs(&stats, n, ties) {
+            println!("{:<5}  {:<6}  {}", guard, minute, count);
+        }
+        return;
+    }
+    let answers = [
+        ("Part 1", part1(&stats, ties)),
+        ("Part 2", part2(&stats, ties)),
+    ];
+    for (part, answer) in answers {
+        match answer {
+            Some((sleepiest_guard, sleepiest_minute)) => println!(
+                "Day 4 {}: guard {} minute {} result {}",
+                part,
+                sleepiest_guard,
+                sleepiest_minute,
+                result(sleepiest_guard, sleepiest_minute)
+            ),
+            None => println!("Day 4 {}: nobody slept in the watch window", part),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const TEST_INPUT: &str = "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] falls asleep\n[1518-11-01 00:25] wakes up\n[1518-11-01 00:30] falls asleep\n[1518-11-01 00:55] wakes up\n[1518-11-01 23:58] Guard #99 begins shift\n[1518-11-02 00:40] falls asleep\n[1518-11-02 00:50] wakes up\n[1518-11-03 00:05] Guard #10 begins shift\n[1518-11-03 00:24] falls asleep\n[1518-11-03 00:29] wakes up\n[1518-11-04 00:02] Guard #99 begins shift\n[1518-11-04 00:36] falls asleep\n[1518-11-04 00:46] wakes up\n[1518-11-05 00:03] Guard #99 begins shift\n[1518-11-05 00:45] falls asleep\n[1518-11-05 00:55] wakes up";
-
     #[test]
     fn test_part1() {
-        assert_eq!((10, 24), part1(TEST_INPUT));
+        assert_eq!(Some((10, 24)), part1(&test_stats(), TieBreak::Lowest));
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!((99, 45), part2(TEST_INPUT));
+        assert_eq!(Some((99, 45)), part2(&test_stats(), TieBreak::Lowest));
+    }
+
+    #[test]
+    fn test_result() {
+        assert_eq!(240, result(10, 24));
+        assert_eq!(4_000_000_000 * 59, result(4_000_000_000, 59));
+    }
+
+    #[test]
+    fn test_asleep_outside_window() {
+        // guard 7 only naps at 22:10, so has no minutes in the midnight hour to pick from
+        let input = "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] falls asleep\n[1518-11-01 00:25] wakes up\n[1518-11-01 22:00] Guard #7 begins shift\n[1518-11-01 22:10] falls asleep\n[1518-11-01 22:20] wakes up";
+        let stats = parse_log(&Input::puzzle(input), Window::MidnightHour);
+        assert_eq!(Some((10, 5)), part1(&stats, TieBreak::Lowest));
+        assert_eq!(Some((10, 5)), part2(&stats, TieBreak::Lowest));
+        let (_, only_seven) = input.split_at(input.find("[1518-11-01 22:00]").unwrap());
+        let stats = parse_log(&Input::puzzle(only_seven), Window::MidnightHour);
+        assert_eq!(None, part1(&stats, TieBreak::Lowest));
+        assert_eq!(None, part2(&stats, TieBreak::Lowest));
     }
 }
@@ -0,0 +1,391 @@
+// Each post keeps its own log and they get combined nightly. The logs can be big and in any order,
+// so this is an external merge sort: read at most `chunk_lines` events at a time, sort them and
+// spill them to a temporary run file, then merge the runs by timestamp. Only one chunk (and then
+// one line per run) is ever held in memory. With more runs than MAX_FAN_IN they are merged in
+// passes, a batch at a time into a bigger run, so there are never more run files open than that.
+// Where several logs recorded the same event it's only written once, but an event repeated within
+// one log is kept, since that log is wrong and validation should say so. Where logs have different
+// events at the same minute that's a conflict: both are kept (validation will then complain about
+// the duplicate timestamp) and it's reported, since we can't tell which station is right.
+// Lines that aren't events can't be placed in time, so they're left out and reported as issues.
+
+use crate::log::{self, Issue, Problem, Timestamp};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const DEFAULT_CHUNK_LINES: usize = 100_000;
+
+// most runs merged (and so run files open) at once
+const MAX_FAN_IN: usize = 64;
+
+// numbers the temp files, so merges running at the same time don't share any
+static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Source {
+    // shown in reports, normally the file name
+    pub name: String,
+    pub reader: Box<dyn BufRead>,
+}
+
+impl Source {
+    // `-` is stdin.
+    pub fn open(path: &str) -> io::Result<Source> {
+        let reader: Box<dyn BufRead> = if path == "-" {
+            Box::new(BufReader::new(io::stdin()))
+        } else {
+            Box::new(BufReader::new(File::open(path)?))
+        };
+        Ok(Source {
+            name: path.to_string(),
+            reader,
+        })
+    }
+}
+
+// Ordered by time first, so runs merge chronologically, and then by text so identical events from
+// different sources end up next to each other.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    time: Timestamp,
+    text: String,
+    source: usize,
+    line: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    pub time: Timestamp,
+    // (source name, line, event text) for each different event at that time
+    pub events: Vec<(String, usize, String)>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: stations disagree:", self.time)?;
+        for (name, line, text) in &self.events {
+            write!(f, " {}:{} {:?}", name, line, text)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct MergeReport {
+    // the source names, which the indices below refer to
+    pub names: Vec<String>,
+    // events written out
+    pub events: usize,
+    // identical events dropped because another source already had them
+    pub duplicates: usize,
+    pub conflicts: Vec<Conflict>,
+    // (source, issue at that source's line) for each line that wasn't a log event, left out
+    pub unrecognised: Vec<(usize, Issue)>,
+    // (source, line) for each line written, in order, if merge_with_origins was asked for them
+    pub origins: Vec<(usize, usize)>,
+}
+
+impl MergeReport {
+    // `north.txt:12`
+    pub fn place(&self, source: usize, line: usize) -> String {
+        format!("{}:{}", self.names[source], line)
+    }
+}
+
+// A sorted run, either still in memory (the last chunk never needs spilling) or in a temp file of
+// `source \t line \t text` lines, which isn't opened until it's read.
+enum Run {
+    Memory(std::vec::IntoIter<Entry>),
+    File(Option<io::Lines<BufReader<File>>>, PathBuf),
+}
+
+impl Run {
+    fn spill(entries: impl Iterator<Item = io::Result<Entry>>) -> io::Result<Run> {
+        let index = NEXT_RUN.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("day4-merge-{}-{}.run", process::id(), index));
+        let mut out = BufWriter::new(File::create(&path)?);
+        // from here on dropping the run removes the file, even if writing it fails
+        let run = Run::File(None, path);
+        for entry in entries {
+            let entry = entry?;
+            writeln!(out, "{}\t{}\t{}", entry.source, entry.line, entry.text)?;
+        }
+        out.flush()?;
+        Ok(run)
+    }
+
+    fn next(&mut self) -> io::Result<Option<Entry>> {
+        match self {
+            Run::Memory(entries) => Ok(entries.next()),
+            Run::File(lines, path) => {
+                if lines.is_none() {
+                    *lines = Some(BufReader::new(File::open(&*path)?).lines());
+                }
+                let line = match lines.as_mut().unwrap().next() {
+                    Some(line) => line?,
+                    None => return Ok(None),
+                };
+                let mut fields = line.splitn(3, '\t');
+                let corrupt = || io::Error::new(io::ErrorKind::InvalidData, "corrupt merge run");
+                let source = fields
+                    .next()
+                    .and_then(|f| f.parse().ok())
+                    .ok_or_else(corrupt)?;
+                let line = fields
+                    .next()
+                    .and_then(|f| f.parse().ok())
+                    .ok_or_else(corrupt)?;
+                let text = fields.next().ok_or_else(corrupt)?.to_string();
+                // it parsed before it was spilled, so the timestamp is the same 16 characters
+                let time = text
+                    .get(1..17)
+                    .and_then(Timestamp::parse)
+                    .ok_or_else(corrupt)?;
+                Ok(Some(Entry {
+                    time,
+                    text,
+                    source,
+                    line,
+                }))
+            }
+        }
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        if let Run::File(_, path) = self {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// k-way merge of sorted runs, smallest entry first.
+struct Merged {
+    runs: Vec<Run>,
+    heap: BinaryHeap<Reverse<(Entry, usize)>>,
+}
+
+impl Merged {
+    fn new(mut runs: Vec<Run>) -> io::Result<Merged> {
+        let mut heap = BinaryHeap::new();
+        for (index, run) in runs.iter_mut().enumerate() {
+            if let Some(entry) = run.next()? {
+                heap.push(Reverse((entry, index)));
+            }
+        }
+        Ok(Merged { runs, heap })
+    }
+}
+
+impl Iterator for Merged {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<io::Result<Entry>> {
+        let Reverse((entry, index)) = self.heap.pop()?;
+        match self.runs[index].next() {
+            Ok(Some(next)) => self.heap.push(Reverse((next, index))),
+            Ok(None) => {}
+            Err(e) => return Some(Err(e)),
+        }
+        Some(Ok(entry))
+    }
+}
+
+// Writes out one minute's worth of entries (sorted, so duplicates are adjacent).
+fn flush(
+    group: &mut Vec<Entry>,
+    report: &mut MergeReport,
+    track_origins: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    group.dedup_by(|later, earlier| {
+        let same = later.text == earlier.text && later.source != earlier.source;
+        if same {
+            report.duplicates += 1;
+        }
+        same
+    });
+    if group.iter().any(|entry| entry.text != group[0].text) {
+        let names = &report.names;
+        report.conflicts.push(Conflict {
+            time: group[0].time,
+            events: group
+                .iter()
+                .map(|entry| {
+                    let text = entry.text.split_once("] ").map_or("", |(_, text)| text);
+                    (names[entry.source].clone(), entry.line, text.to_string())
+                })
+                .collect(),
+        });
+    }
+    for entry in group.drain(..) {
+        writeln!(out, "{}", entry.text)?;
+        report.events += 1;
+        if track_origins {
+            report.origins.push((entry.source, entry.line));
+        }
+    }
+    Ok(())
+}
+
+// Merges the sources into one time-ordered log written to `out`.
+pub fn merge(
+    sources: Vec<Source>,
+    chunk_lines: usize,
+    out: &mut impl Write,
+) -> io::Result<MergeReport> {
+    merge_with_fan_in(sources, chunk_lines, MAX_FAN_IN, false, out)
+}
+
+// Like merge, but also records where each line written came from, which costs memory for every
+// event, so only for logs that are going to be analysed in memory anyway.
+pub fn merge_with_origins(
+    sources: Vec<Source>,
+    chunk_lines: usize,
+    out: &mut impl Write,
+) -> io::Result<MergeReport> {
+    merge_with_fan_in(sources, chunk_lines, MAX_FAN_IN, true, out)
+}
+
+fn merge_with_fan_in(
+    sources: Vec<Source>,
+    chunk_lines: usize,
+    fan_in: usize,
+    track_origins: bool,
+    out: &mut impl Write,
+) -> io::Result<MergeReport> {
+    let regex = log::line_regex();
+    let mut report = MergeReport {
+        names: sources.iter().map(|source| source.name.clone()).collect(),
+        ..MergeReport::default()
+    };
+
+    // step 1: sorted runs of at most chunk_lines events
+    let mut runs: Vec<Run> = Vec::new();
+    let mut chunk: Vec<Entry> = Vec::new();
+    for (source, input) in sources.into_iter().enumerate() {
+        for (index, text) in input.reader.lines().enumerate() {
+            let text = text?;
+            let text = text.trim_end();
+            if text.trim().is_empty() {
+                continue;
+            }
+            match log::parse_line(&regex, text, index + 1) {
+                Some(event) => chunk.push(Entry {
+                    time: event.time,
+                    text: text.to_string(),
+                    source,
+                    line: index + 1,
+                }),
+                None => report.unrecognised.push((
+                    source,
+                    Issue {
+                        line: index + 1,
+                        problem: Problem::Unrecognised,
+                    },
+                )),
+            }
+            if chunk.len() >= chunk_lines.max(1) {
+                chunk.sort_unstable();
+                runs.push(Run::spill(chunk.drain(..).map(Ok))?);
+            }
+        }
+    }
+    chunk.sort_unstable();
+    runs.push(Run::Memory(chunk.into_iter()));
+
+    // step 2: while there are too many runs to merge at once, merge the oldest into a new one
+    let fan_in = fan_in.max(2);
+    while runs.len() > fan_in {
+        let batch: Vec<Run> = runs.drain(..fan_in).collect();
+        runs.push(Run::spill(Merged::new(batch)?)?);
+    }
+
+    // step 3: k-way merge of what's left, a minute at a time
+    let mut group: Vec<Entry> = Vec::new();
+    for entry in Merged::new(runs)? {
+        let entry = entry?;
+        if group.first().is_some_and(|first| first.time != entry.time) {
+            flush(&mut group, &mut report, track_origins, out)?;
+        }
+        group.push(entry);
+    }
+    flush(&mut group, &mut report, track_origins, out)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(name: &str, text: &'static str) -> Source {
+        Source {
+            name: name.to_string(),
+            reader: Box::new(text.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let north = "[1518-11-01 00:05] falls asleep\n\
+                     [1518-11-01 00:00] Guard #10 begins shift\n\
+                     [1518-11-02 00:40] falls asleep\n\
+                     garbage\n\
+                     [1518-11-01 00:25] wakes up";
+        let south = "[1518-11-01 23:58] Guard #99 begins shift\n\
+                     [1518-11-01 00:25] wakes up\n\
+                     [1518-11-02 00:40] wakes up\n\
+                     [1518-11-02 00:50] wakes up\n\
+                     [1518-11-02 00:50] wakes up";
+        // one line a run with only two merged at a time takes several passes
+        for &(chunk_lines, fan_in) in &[(1, 2), (2, 3), (DEFAULT_CHUNK_LINES, MAX_FAN_IN)] {
+            let mut out = Vec::new();
+            let sources = vec![source("north", north), source("south", south)];
+            let report = merge_with_fan_in(sources, chunk_lines, fan_in, true, &mut out).unwrap();
+            assert_eq!(
+                "[1518-11-01 00:00] Guard #10 begins shift\n\
+                 [1518-11-01 00:05] falls asleep\n\
+                 [1518-11-01 00:25] wakes up\n\
+                 [1518-11-01 23:58] Guard #99 begins shift\n\
+                 [1518-11-02 00:40] falls asleep\n\
+                 [1518-11-02 00:40] wakes up\n\
+                 [1518-11-02 00:50] wakes up\n\
+                 [1518-11-02 00:50] wakes up\n",
+                String::from_utf8(out).unwrap()
+            );
+            // the 00:25 wake up is in both logs, but south's repeated 00:50 one is south's mistake
+            assert_eq!((8, 1), (report.events, report.duplicates));
+            assert_eq!(
+                vec![
+                    (0, 2),
+                    (0, 1),
+                    (0, 5),
+                    (1, 1),
+                    (0, 3),
+                    (1, 3),
+                    (1, 4),
+                    (1, 5)
+                ],
+                report.origins
+            );
+            let unrecognised: Vec<String> = report
+                .unrecognised
+                .iter()
+                .map(|(source, issue)| issue.describe(&|line| report.place(*source, line)))
+                .collect();
+            assert_eq!(vec!["north:4: unrecognised log line"], unrecognised);
+            assert_eq!(1, report.conflicts.len());
+            assert_eq!(
+                "1518-11-02 00:40: stations disagree: north:3 \"falls asleep\" south:3 \"wakes up\"",
+                report.conflicts[0].to_string()
+            );
+        }
+    }
+}
@@ -0,0 +1,214 @@
+// How likely is a guard to be asleep in each minute of their next shift? The estimate is simply
+// the fraction of their past shifts they were asleep in it, with a 95% Wilson score interval
+// around it (which behaves sensibly for guards with only a handful of shifts, unlike the usual
+// p ± 1.96 standard errors). Guards on duty together are assumed to doze independently.
+// backtest trains on the first part of a log and scores the predictions against the rest.
+
+use crate::sleep::{Shift, Window};
+use crate::stats::{self, Stats};
+use crate::{GuardId, Minute};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// 95% two-sided
+const Z: f64 = 1.96;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Prediction {
+    pub minute: Minute,
+    pub probability: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+fn wilson(asleep: u32, shifts: u32) -> (f64, f64) {
+    let n = shifts as f64;
+    let p = asleep as f64 / n;
+    let denominator = 1.0 + Z * Z / n;
+    let centre = (p + Z * Z / (2.0 * n)) / denominator;
+    let spread = Z * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt() / denominator;
+    ((centre - spread).max(0.0), (centre + spread).min(1.0))
+}
+
+pub struct Model {
+    pub stats: Stats,
+    pub window: Window,
+}
+
+impl Model {
+    pub fn train(shifts: &[Shift], window: Window) -> Model {
+        Model {
+            stats: stats::guard_stats(shifts, window),
+            window,
+        }
+    }
+
+    // Every minute of the window for this guard, most likely asleep first (earliest first among
+    // equals), or None for a guard we've never seen.
+    pub fn predict(&self, guard: GuardId) -> Option<Vec<Prediction>> {
+        let guard_stats = self.stats.get(&guard)?;
+        let mut predictions: Vec<Prediction> = (0..self.window.size())
+            .map(|minute| {
+                let asleep = *guard_stats.histogram.get(&minute).unwrap_or(&0);
+                let (low, high) = wilson(asleep.min(guard_stats.shifts), guard_stats.shifts);
+                Prediction {
+                    minute,
+                    probability: guard_stats.probability_asleep(minute).min(1.0),
+                    low,
+                    high,
+                }
+            })
+            .collect();
+        predictions.sort_by(|a, b| {
+            b.probability
+                .partial_cmp(&a.probability)
+                .unwrap()
+                .then(a.minute.cmp(&b.minute))
+        });
+        Some(predictions)
+    }
+
+    // The minute every guard on duty is most likely to be asleep in at once, with that chance.
+    // Guards we've never seen can't be predicted, so they're an error rather than a guess.
+    pub fn best_minute(&self, on_duty: &[GuardId]) -> Result<(Minute, f64), GuardId> {
+        let mut all_asleep = vec![1.0; self.window.size() as usize];
+        for &guard in on_duty {
+            let guard_stats = self.stats.get(&guard).ok_or(guard)?;
+            for (minute, chance) in all_asleep.iter_mut().enumerate() {
+                *chance *= guard_stats.probability_asleep(minute as Minute).min(1.0);
+            }
+        }
+        let mut best = (0, all_asleep[0]);
+        for (minute, &chance) in all_asleep.iter().enumerate() {
+            if chance > best.1 {
+                best = (minute as Minute, chance);
+            }
+        }
+        Ok(best)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Backtest {
+    pub training_shifts: usize,
+    // test shifts by guards seen in training, the only ones that can be scored
+    pub scored_shifts: usize,
+    pub unscored_shifts: usize,
+    // mean squared error of every minute's probability against what happened (0 is perfect, and
+    // always guessing 0.5 scores 0.25)
+    pub brier_score: f64,
+    // how often the guard really was asleep in the minute we rated most likely
+    pub top_minute_hit_rate: f64,
+    // how often a guard's rate of sleeping in a minute over the test shifts fell inside the
+    // interval we gave for it
+    pub interval_coverage: f64,
+}
+
+// Trains on the first `fraction` of the shifts (in time order) and tests on the rest.
+pub fn backtest(shifts: &[Shift], window: Window, fraction: f64) -> Backtest {
+    let split = ((shifts.len() as f64 * fraction).round() as usize).min(shifts.len());
+    let (training, testing) = shifts.split_at(split);
+    let model = Model::train(training, window);
+    let mut scored = 0;
+    let (mut squared_error, mut predictions) = (0.0, 0);
+    let mut hits = 0;
+    // per guard: test shifts, and how many of them they were asleep in each minute
+    let mut outcomes: BTreeMap<GuardId, (u32, HashMap<Minute, u32>)> = BTreeMap::new();
+    for shift in testing {
+        let guess = match model.predict(shift.guard) {
+            Some(guess) => guess,
+            None => continue,
+        };
+        scored += 1;
+        let asleep: HashSet<Minute> = shift
+            .naps
+            .iter()
+            .flat_map(|nap| window.buckets(nap))
+            .collect();
+        if asleep.contains(&guess[0].minute) {
+            hits += 1;
+        }
+        for prediction in &guess {
+            let outcome = if asleep.contains(&prediction.minute) {
+                1.0
+            } else {
+                0.0
+            };
+            squared_error += (prediction.probability - outcome).powi(2);
+            predictions += 1;
+        }
+        let (nights, counts) = outcomes.entry(shift.guard).or_default();
+        *nights += 1;
+        for minute in asleep {
+            *counts.entry(minute).or_insert(0) += 1;
+        }
+    }
+    let (mut covered, mut intervals) = (0, 0);
+    for (guard, (nights, counts)) in &outcomes {
+        for prediction in model.predict(*guard).unwrap() {
+            let rate = *counts.get(&prediction.minute).unwrap_or(&0) as f64 / *nights as f64;
+            if prediction.low <= rate && rate <= prediction.high {
+                covered += 1;
+            }
+            intervals += 1;
+        }
+    }
+    let ratio = |count: usize, total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            count as f64 / total as f64
+        }
+    };
+    Backtest {
+        training_shifts: training.len(),
+        scored_shifts: scored,
+        unscored_shifts: testing.len() - scored,
+        brier_score: if predictions == 0 {
+            0.0
+        } else {
+            squared_error / predictions as f64
+        },
+        top_minute_hit_rate: ratio(hits, scored),
+        interval_coverage: ratio(covered, intervals),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_shifts;
+
+    #[test]
+    fn test_predict() {
+        let shifts = test_shifts();
+        let model = Model::train(&shifts, Window::MidnightHour);
+        let predictions = model.predict(99).unwrap();
+        assert_eq!(60, predictions.len());
+        assert_eq!(45, predictions[0].minute);
+        assert_eq!(1.0, predictions[0].probability);
+        // 3 out of 3 is encouraging but hardly certain
+        assert!(predictions[0].low > 0.4 && predictions[0].low < 0.5);
+        assert!(predictions[0].high > 0.99);
+        assert_eq!(None, model.predict(7));
+        assert_eq!(Ok((45, 0.5)), model.best_minute(&[10, 99]));
+        assert_eq!(Err(7), model.best_minute(&[10, 7]));
+    }
+
+    #[test]
+    fn test_backtest() {
+        let shifts = test_shifts();
+        let result = backtest(&shifts, Window::MidnightHour, 0.6);
+        assert_eq!(
+            (3, 2, 0),
+            (
+                result.training_shifts,
+                result.scored_shifts,
+                result.unscored_shifts
+            )
+        );
+        // only guard 99 is on duty afterwards, and their one training night makes 00:40 the best
+        // guess, which is right the first night and wrong the second
+        assert_eq!(0.5, result.top_minute_hit_rate);
+        assert!(result.brier_score > 0.0 && result.brier_score < 0.25);
+    }
+}
@@ -0,0 +1,311 @@
+// A few questions about the log beyond the two puzzle strategies, asked in something close to
+// English:
+//   asleep at 1518-05-19 00:37
+//   slept during minute 45 on more than 3 nights
+//   shifts of #2447 between 1518-03-01 and 1518-04-01
+// Keywords are case insensitive, the `#` is optional and both dates are inclusive. Answers come
+// back as a table that can be printed as text or JSON.
+
+use crate::log::{Date, Timestamp};
+use crate::sleep::Shift;
+use crate::{GuardId, Minute};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Query {
+    AsleepAt(Timestamp),
+    SleptDuring {
+        minute: Minute,
+        more_than: u32,
+    },
+    ShiftsOf {
+        guard: GuardId,
+        from: Date,
+        to: Date,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    Number(u64),
+    Text(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Table {
+    pub columns: Vec<&'static str>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+fn number<T: std::str::FromStr>(token: Option<&str>, what: &str) -> Result<T, String> {
+    token
+        .and_then(|token| token.parse().ok())
+        .ok_or_else(|| format!("expected {}", what))
+}
+
+fn date(token: Option<&str>) -> Result<Date, String> {
+    token
+        .and_then(Date::parse)
+        .ok_or_else(|| "expected a date like 1518-05-19".to_string())
+}
+
+// Checks the words starting at tokens[at] are the expected ones.
+fn words(tokens: &[&str], at: usize, expected: &[&str]) -> Result<(), String> {
+    for (i, word) in expected.iter().enumerate() {
+        if !tokens
+            .get(at + i)
+            .is_some_and(|token| token.eq_ignore_ascii_case(word))
+        {
+            return Err(format!("expected \"{}\"", expected.join(" ")));
+        }
+    }
+    Ok(())
+}
+
+fn end(tokens: &[&str], length: usize) -> Result<(), String> {
+    match tokens.get(length) {
+        Some(extra) => Err(format!("unexpected \"{}\"", extra)),
+        None => Ok(()),
+    }
+}
+
+pub fn parse(text: &str) -> Result<Query, String> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let token = |i: usize| tokens.get(i).cloned();
+    let query = match token(0).map(str::to_ascii_lowercase).as_deref() {
+        Some("asleep") => {
+            words(&tokens, 0, &["asleep", "at"])?;
+            let time = match (token(2), token(3)) {
+                (Some(date), Some(time)) => Timestamp::parse(&format!("{} {}", date, time)),
+                _ => None,
+            };
+            end(&tokens, 4)?;
+            Query::AsleepAt(time.ok_or("expected a time like 1518-05-19 00:37")?)
+        }
+        Some("slept") => {
+            words(&tokens, 0, &["slept", "during", "minute"])?;
+            let minute = number(token(3), "a minute")?;
+            words(&tokens, 4, &["on", "more", "than"])?;
+            let more_than = number(token(7), "a number of nights")?;
+            words(&tokens, 8, &["nights"])?;
+            end(&tokens, 9)?;
+            Query::SleptDuring { minute, more_than }
+        }
+        Some("shifts") => {
+            words(&tokens, 0, &["shifts", "of"])?;
+            let guard = number(token(2).map(|t| t.trim_start_matches('#')), "a guard id")?;
+            words(&tokens, 3, &["between"])?;
+            let from = date(token(4))?;
+            words(&tokens, 5, &["and"])?;
+            let to = date(token(6))?;
+            end(&tokens, 7)?;
+            Query::ShiftsOf { guard, from, to }
+        }
+        _ => return Err("queries start with asleep, slept or shifts".to_string()),
+    };
+    Ok(query)
+}
+
+fn clock(time: Timestamp) -> String {
+    format!("{:02}:{:02}", time.hour, time.minute)
+}
+
+pub fn run(query: &Query, shifts: &[Shift]) -> Table {
+    match *query {
+        Query::AsleepAt(time) => {
+            let time = time.to_minutes();
+            let rows = shifts
+                .iter()
+                .flat_map(|shift| shift.naps.iter())
+                .filter(|nap| nap.start.to_minutes() <= time && time < nap.end.to_minutes())
+                .map(|nap| {
+                    vec![
+                        Value::Number(nap.guard as u64),
+                        Value::Text(nap.start.to_string()),
+                        Value::Text(nap.end.to_string()),
+                    ]
+                })
+                .collect();
+            Table {
+                columns: vec!["guard", "asleep from", "until"],
+                rows,
+            }
+        }
+        Query::SleptDuring { minute, more_than } => {
+            let mut nights: BTreeMap<GuardId, u32> = BTreeMap::new();
+            for shift in shifts.iter().filter(|shift| shift.asleep_at(minute)) {
+                *nights.entry(shift.guard).or_insert(0) += 1;
+            }
+            let mut nights: Vec<(GuardId, u32)> =
+                nights.into_iter().filter(|&(_, n)| n > more_than).collect();
+            nights.sort_by_key(|&(guard, n)| (std::cmp::Reverse(n), guard));
+            Table {
+                columns: vec!["guard", "nights"],
+                rows: nights
+                    .into_iter()
+                    .map(|(guard, n)| vec![Value::Number(guard as u64), Value::Number(n as u64)])
+                    .collect(),
+            }
+        }
+        Query::ShiftsOf { guard, from, to } => {
+            let rows = shifts
+                .iter()
+                .filter(|shift| shift.guard == guard && from <= shift.date && shift.date <= to)
+                .map(|shift| {
+                    let naps: Vec<String> = shift
+                        .naps
+                        .iter()
+                        .map(|nap| format!("{}-{}", clock(nap.start), clock(nap.end)))
+                        .collect();
+                    vec![
+                        Value::Text(shift.date.to_string()),
+                        Value::Text(shift.begins.to_string()),
+                        Value::Number(shift.naps.iter().map(|nap| nap.minutes() as u64).sum()),
+                        Value::Text(naps.join(" ")),
+                    ]
+                })
+                .collect();
+            Table {
+                columns: vec!["date", "begins", "minutes asleep", "naps"],
+                rows,
+            }
+        }
+    }
+}
+
+pub fn json_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Table {
+    pub fn to_text(&self) -> String {
+        let mut widths: Vec<usize> = self.columns.iter().map(|c| c.len()).collect();
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|value| value.to_string()).collect())
+            .collect();
+        for row in &cells {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        let line = |row: Vec<&str>| {
+            let padded: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            padded.join("  ").trim_end().to_string() + "\n"
+        };
+        let mut out = line(self.columns.clone());
+        for row in &cells {
+            out.push_str(&line(row.iter().map(String::as_str).collect()));
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let objects: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let pairs: Vec<String> = self
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, value)| {
+                        let value = match value {
+                            Value::Number(n) => n.to_string(),
+                            Value::Text(text) => json_string(text),
+                        };
+                        format!("{}: {}", json_string(column), value)
+                    })
+                    .collect();
+                format!("  {{{}}}", pairs.join(", "))
+            })
+            .collect();
+        if objects.is_empty() {
+            "[]\n".to_string()
+        } else {
+            format!("[\n{}\n]\n", objects.join(",\n"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::read_events;
+    use crate::sleep::shifts;
+    use crate::test_shifts;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Ok(Query::SleptDuring {
+                minute: 45,
+                more_than: 1
+            }),
+            parse("Slept during minute 45 on more than 1 nights")
+        );
+        assert_eq!(
+            Err("expected \"on more than\"".to_string()),
+            parse("slept during minute 45 on 2 nights")
+        );
+        assert!(parse("asleep at 1518-11-01 25:00").is_err());
+        assert!(parse("who").is_err());
+        assert_eq!(
+            Err("unexpected \"please\"".to_string()),
+            parse("shifts of 10 between 1518-11-01 and 1518-11-02 please")
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        let shifts = test_shifts();
+        let ask = |text| run(&parse(text).unwrap(), &shifts);
+        assert_eq!(
+            "guard  nights\n99     3\n",
+            ask("slept during minute 45 on more than 1 nights").to_text()
+        );
+        assert_eq!(
+            "[\n  {\"guard\": 10, \"asleep from\": \"1518-11-01 00:05\", \"until\": \"1518-11-01 00:25\"}\n]\n",
+            ask("asleep at 1518-11-01 00:24").to_json()
+        );
+        assert_eq!("[]\n", ask("asleep at 1518-11-01 00:25").to_json());
+        assert_eq!(
+            "date        begins            minutes asleep  naps\n\
+             1518-11-03  1518-11-03 00:05  5               00:24-00:29\n",
+            ask("shifts of #10 between 1518-11-02 and 1518-11-30").to_text()
+        );
+    }
+
+    #[test]
+    fn test_shift_after_one() {
+        // a shift starting after 01:00 is still that night's
+        let late = shifts(
+            &read_events("[1518-11-03 01:30] Guard #5 begins shift\n[1518-11-03 01:40] falls asleep\n[1518-11-03 01:45] wakes up")
+                .unwrap(),
+        );
+        assert_eq!(
+            "date        begins            minutes asleep  naps\n\
+             1518-11-03  1518-11-03 01:30  5               01:40-01:45\n",
+            run(
+                &parse("shifts of 5 between 1518-11-03 and 1518-11-03").unwrap(),
+                &late
+            )
+            .to_text()
+        );
+    }
+}
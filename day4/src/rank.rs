@@ -0,0 +1,197 @@
+// Rankings over the guard stats that don't depend on HashMap iteration order. Whenever two guards
+// or minutes have the same score the TieBreak decides, and a Pick says who else it could have been.
+
+use crate::stats::Stats;
+use crate::{GuardId, Minute};
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TieBreak {
+    // lowest guard id, then earliest minute
+    #[default]
+    Lowest,
+    // highest guard id, then latest minute
+    Highest,
+}
+
+impl TieBreak {
+    pub fn from_name(name: &str) -> Option<TieBreak> {
+        match name {
+            "lowest" => Some(TieBreak::Lowest),
+            "highest" => Some(TieBreak::Highest),
+            _ => None,
+        }
+    }
+
+    fn order<T: Ord>(self, a: &T, b: &T) -> Ordering {
+        match self {
+            TieBreak::Lowest => a.cmp(b),
+            TieBreak::Highest => b.cmp(a),
+        }
+    }
+
+    // Highest score first, then the tie break between the items themselves.
+    fn sort<T: Ord>(self, ranked: &mut [(T, u32)]) {
+        ranked.sort_by(|a, b| Reverse(a.1).cmp(&Reverse(b.1)).then(self.order(&a.0, &b.0)));
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Pick<T> {
+    pub winner: T,
+    pub score: u32,
+    // everything else with the same score, in tie break order
+    pub tied_with: Vec<T>,
+}
+
+fn pick<T: Copy>(ranked: &[(T, u32)]) -> Option<Pick<T>> {
+    let &(winner, score) = ranked.first()?;
+    let tied_with = ranked[1..]
+        .iter()
+        .take_while(|(_, other)| *other == score)
+        .map(|&(item, _)| item)
+        .collect();
+    Some(Pick {
+        winner,
+        score,
+        tied_with,
+    })
+}
+
+// The highest scoring item, for rankings other than the ones below.
+pub fn best<T: Ord + Copy>(
+    items: impl IntoIterator<Item = (T, u32)>,
+    ties: TieBreak,
+) -> Option<Pick<T>> {
+    let mut ranked: Vec<(T, u32)> = items.into_iter().collect();
+    ties.sort(&mut ranked);
+    pick(&ranked)
+}
+
+// Every guard with their total minutes asleep, sleepiest first.
+pub fn guards_by_total(stats: &Stats, ties: TieBreak) -> Vec<(GuardId, u32)> {
+    let mut ranked: Vec<(GuardId, u32)> = stats
+        .iter()
+        .map(|(&guard, guard_stats)| (guard, guard_stats.minutes_asleep))
+        .collect();
+    ties.sort(&mut ranked);
+    ranked
+}
+
+// One guard's minutes by how many times they were asleep in them.
+pub fn minutes_by_count(counts: &HashMap<Minute, u32>, ties: TieBreak) -> Vec<(Minute, u32)> {
+    let mut ranked: Vec<(Minute, u32)> = counts.iter().map(|(&m, &count)| (m, count)).collect();
+    ties.sort(&mut ranked);
+    ranked
+}
+
+// Every (guard, minute) pair by how many times that guard was asleep in that minute.
+pub fn pairs_by_count(stats: &Stats, ties: TieBreak) -> Vec<((GuardId, Minute), u32)> {
+    let mut ranked: Vec<((GuardId, Minute), u32)> = stats
+        .iter()
+        .flat_map(|(&guard, guard_stats)| {
+            guard_stats
+                .histogram
+                .iter()
+                .map(move |(&m, &count)| ((guard, m), count))
+        })
+        .collect();
+    ties.sort(&mut ranked);
+    ranked
+}
+
+pub fn top_guards(stats: &Stats, n: usize, ties: TieBreak) -> Vec<(GuardId, u32)> {
+    let mut ranked = guards_by_total(stats, ties);
+    ranked.truncate(n);
+    ranked
+}
+
+pub fn top_pairs(stats: &Stats, n: usize, ties: TieBreak) -> Vec<((GuardId, Minute), u32)> {
+    let mut ranked = pairs_by_count(stats, ties);
+    ranked.truncate(n);
+    ranked
+}
+
+pub fn sleepiest_guard(stats: &Stats, ties: TieBreak) -> Option<Pick<GuardId>> {
+    pick(&guards_by_total(stats, ties))
+}
+
+pub fn sleepiest_minute(counts: &HashMap<Minute, u32>, ties: TieBreak) -> Option<Pick<Minute>> {
+    pick(&minutes_by_count(counts, ties))
+}
+
+pub fn most_frequent_pair(stats: &Stats, ties: TieBreak) -> Option<Pick<(GuardId, Minute)>> {
+    pick(&pairs_by_count(stats, ties))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::log::{read_events, Date};
+    use crate::sleep::{shifts, Window};
+    use crate::stats::guard_stats;
+
+    // One night per (guard, fell asleep, woke up) with the minutes past midnight, run through the
+    // same log parsing as the real thing.
+    fn stats(nights: &[(GuardId, Minute, Minute)]) -> Stats {
+        let first = Date::parse("1518-11-01").unwrap().to_days();
+        let mut log = String::new();
+        for (night, &(guard, asleep, awake)) in nights.iter().enumerate() {
+            let date = Date::from_days(first + night as i64);
+            log.push_str(&format!(
+                "[{} 00:00] Guard #{} begins shift\n[{} 00:{:02}] falls asleep\n[{} 00:{:02}] wakes up\n",
+                date, guard, date, asleep, date, awake
+            ));
+        }
+        guard_stats(&shifts(&read_events(&log).unwrap()), Window::MidnightHour)
+    }
+
+    #[test]
+    fn test_ties() {
+        // guard 3 asleep at minute 40 four times, 5 at minute 10 three times and 11 once, and 8 at
+        // minutes 20 and 21 twice each, so all three slept 4 minutes
+        let stats = stats(&[
+            (5, 10, 11),
+            (3, 40, 41),
+            (5, 10, 11),
+            (8, 20, 22),
+            (3, 40, 41),
+            (5, 10, 12),
+            (3, 40, 41),
+            (8, 20, 22),
+            (3, 40, 41),
+        ]);
+        let lowest = sleepiest_guard(&stats, TieBreak::Lowest).unwrap();
+        assert_eq!(
+            (3, 4, vec![5, 8]),
+            (lowest.winner, lowest.score, lowest.tied_with)
+        );
+        assert_eq!(
+            8,
+            sleepiest_guard(&stats, TieBreak::Highest).unwrap().winner
+        );
+        assert_eq!(
+            Pick {
+                winner: 10,
+                score: 3,
+                tied_with: vec![]
+            },
+            sleepiest_minute(&stats[&5].histogram, TieBreak::Lowest).unwrap()
+        );
+        let pair = most_frequent_pair(&stats, TieBreak::Lowest).unwrap();
+        assert_eq!(
+            ((3, 40), 4, vec![]),
+            (pair.winner, pair.score, pair.tied_with)
+        );
+        assert_eq!(
+            vec![((3, 40), 4), ((5, 10), 3), ((8, 20), 2)],
+            top_pairs(&stats, 3, TieBreak::Lowest)
+        );
+        assert_eq!(
+            vec![(8, 4), (5, 4)],
+            top_guards(&stats, 2, TieBreak::Highest)
+        );
+    }
+}
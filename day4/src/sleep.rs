@@ -0,0 +1,216 @@
+// Naps as intervals between two full timestamps, so falling asleep at 23:58 or sleeping through
+// 01:00 is recorded as it happened instead of as a run of minutes within the midnight hour.
+
+use crate::log::{Date, Event, EventKind, Timestamp};
+use crate::{GuardId, Minute};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Nap {
+    pub guard: GuardId,
+    // asleep from the start minute up to but not including the end minute
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+impl Nap {
+    pub fn minutes(&self) -> u32 {
+        (self.end.to_minutes() - self.start.to_minutes()) as u32
+    }
+}
+
+// One guard's watch, kept whole so it can be shown night by night.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shift {
+    pub guard: GuardId,
+    // the night it covers, named (like the puzzle does) after the date of its midnight hour, so a
+    // shift beginning at 23:58 belongs to the next day. Shifts beginning in the afternoon or
+    // evening are for the coming night, and ones beginning in the small hours for the current one.
+    pub date: Date,
+    pub begins: Timestamp,
+    pub naps: Vec<Nap>,
+}
+
+impl Shift {
+    // Whether the guard was asleep at 00:mm on the night of the shift.
+    pub fn asleep_at(&self, minute: Minute) -> bool {
+        let time = self.date.to_days() * 1440 + minute as i64;
+        self.naps
+            .iter()
+            .any(|nap| nap.start.to_minutes() <= time && time < nap.end.to_minutes())
+    }
+}
+
+// Which minute "bucket" each minute asleep is counted in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    // 00:00 to 00:59, bucketed 0-59, the only hour the puzzle cares about. Anything outside it is
+    // ignored.
+    MidnightHour,
+    // the whole day, bucketed 0-1439 by minute since midnight
+    MinuteOfDay,
+}
+
+impl Window {
+    pub fn from_name(name: &str) -> Option<Window> {
+        match name {
+            "midnight" => Some(Window::MidnightHour),
+            "day" => Some(Window::MinuteOfDay),
+            _ => None,
+        }
+    }
+
+    // How many buckets there are.
+    pub fn size(self) -> Minute {
+        match self {
+            Window::MidnightHour => 60,
+            Window::MinuteOfDay => 1440,
+        }
+    }
+
+    // The buckets a nap falls in, one per minute asleep (so a bucket repeats if it spans days).
+    pub fn buckets(self, nap: &Nap) -> impl Iterator<Item = Minute> {
+        (nap.start.to_minutes()..nap.end.to_minutes()).filter_map(move |minute| {
+            let minute_of_day = minute.rem_euclid(1440) as Minute;
+            match self {
+                Window::MidnightHour if minute_of_day < 60 => Some(minute_of_day),
+                Window::MidnightHour => None,
+                Window::MinuteOfDay => Some(minute_of_day),
+            }
+        })
+    }
+}
+
+// Splits the log into shifts, pairing up each fall-asleep with the following wake-up. Expects
+// events that have passed log::check; anything unpaired is dropped.
+pub fn shifts(events: &[Event]) -> Vec<Shift> {
+    let mut shifts: Vec<Shift> = Vec::new();
+    let mut asleep: Option<Timestamp> = None;
+    for event in events {
+        match event.kind {
+            EventKind::BeginsShift(guard) => {
+                let days = event.time.date.to_days();
+                shifts.push(Shift {
+                    guard,
+                    date: Date::from_days(if event.time.hour >= 12 {
+                        days + 1
+                    } else {
+                        days
+                    }),
+                    begins: event.time,
+                    naps: Vec::new(),
+                });
+                asleep = None;
+            }
+            EventKind::FallsAsleep => asleep = Some(event.time),
+            EventKind::WakesUp => {
+                if let (Some(shift), Some(start)) = (shifts.last_mut(), asleep.take()) {
+                    shift.naps.push(Nap {
+                        guard: shift.guard,
+                        start,
+                        end: event.time,
+                    });
+                }
+            }
+        }
+    }
+    shifts
+}
+
+pub fn naps(shifts: &[Shift]) -> Vec<Nap> {
+    shifts.iter().flat_map(|shift| shift.naps.clone()).collect()
+}
+
+// How many times each guard was asleep in each bucket of the window. Guards whose naps all fall
+// outside the window are left out rather than given an empty count.
+pub fn minute_counts(naps: &[Nap], window: Window) -> HashMap<GuardId, HashMap<Minute, u32>> {
+    let mut record: HashMap<GuardId, HashMap<Minute, u32>> = HashMap::new();
+    for nap in naps {
+        for bucket in window.buckets(nap) {
+            *record
+                .entry(nap.guard)
+                .or_default()
+                .entry(bucket)
+                .or_insert(0) += 1;
+        }
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::read_events;
+
+    const LONG_WATCH: &str = "[1518-11-01 23:50] Guard #7 begins shift\n\
+                              [1518-11-01 23:58] falls asleep\n\
+                              [1518-11-02 00:03] wakes up\n\
+                              [1518-11-02 00:50] falls asleep\n\
+                              [1518-11-02 01:10] wakes up";
+
+    #[test]
+    fn test_naps_across_midnight() {
+        let shifts = shifts(&read_events(LONG_WATCH).unwrap());
+        assert_eq!("1518-11-02", shifts[0].date.to_string());
+        assert!(shifts[0].asleep_at(2) && !shifts[0].asleep_at(3));
+        let naps = naps(&shifts);
+        let lengths: Vec<usize> = naps
+            .iter()
+            .map(|nap| Window::MinuteOfDay.buckets(nap).count())
+            .collect();
+        assert_eq!(vec![5, 20], lengths);
+        assert_eq!(
+            vec![5, 20],
+            naps.iter().map(Nap::minutes).collect::<Vec<_>>()
+        );
+
+        let midnight = minute_counts(&naps, Window::MidnightHour);
+        let mut minutes: Vec<Minute> = midnight[&7].keys().cloned().collect();
+        minutes.sort_unstable();
+        let expected: Vec<Minute> = (0..3).chain(50..60).collect();
+        assert_eq!(expected, minutes);
+
+        let day = minute_counts(&naps, Window::MinuteOfDay);
+        assert_eq!(25, day[&7].values().sum::<u32>());
+        assert_eq!(Some(&1), day[&7].get(&1438));
+        assert_eq!(Some(&1), day[&7].get(&69));
+        assert_eq!(None, day[&7].get(&70));
+        assert_eq!(
+            vec![1438, 1439, 0],
+            Window::MinuteOfDay
+                .buckets(&naps[0])
+                .take(3)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_asleep_outside_window() {
+        let input = "[1518-11-01 00:00] Guard #10 begins shift\n\
+                     [1518-11-01 00:05] falls asleep\n\
+                     [1518-11-01 00:25] wakes up\n\
+                     [1518-11-01 22:00] Guard #7 begins shift\n\
+                     [1518-11-01 22:10] falls asleep\n\
+                     [1518-11-01 22:20] wakes up";
+        let naps = naps(&shifts(&read_events(input).unwrap()));
+        let midnight = minute_counts(&naps, Window::MidnightHour);
+        assert_eq!(vec![&10], midnight.keys().collect::<Vec<_>>());
+        assert_eq!(10, minute_counts(&naps, Window::MinuteOfDay)[&7].len());
+    }
+
+    #[test]
+    fn test_shift_dates() {
+        let input = "[1518-11-01 23:58] Guard #5 begins shift\n\
+                     [1518-11-03 01:30] Guard #5 begins shift\n\
+                     [1518-11-03 11:59] Guard #5 begins shift\n\
+                     [1518-11-03 12:00] Guard #5 begins shift";
+        let dates: Vec<String> = shifts(&read_events(input).unwrap())
+            .iter()
+            .map(|shift| shift.date.to_string())
+            .collect();
+        assert_eq!(
+            vec!["1518-11-02", "1518-11-03", "1518-11-03", "1518-11-04"],
+            dates
+        );
+    }
+}
@@ -0,0 +1,107 @@
+// Everything we know about each guard's sleeping, worked out once from their shifts so reports
+// (and both puzzle strategies) are just queries over it.
+
+use crate::log::Date;
+use crate::sleep::{self, Shift, Window};
+use crate::{GuardId, Minute};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardStats {
+    pub shifts: u32,
+    // counted within the watch window, like the histogram
+    pub minutes_asleep: u32,
+    // every minute asleep on shift, inside the window or not
+    pub total_asleep: u32,
+    // the longest single nap in minutes, whether or not it was all inside the window
+    pub longest_nap: u32,
+    // how many shifts the guard was asleep in each minute of the window
+    pub histogram: HashMap<Minute, u32>,
+    pub first_shift: Date,
+    pub last_shift: Date,
+}
+
+impl GuardStats {
+    // Minutes asleep in the window per shift.
+    pub fn average_asleep(&self) -> f64 {
+        self.minutes_asleep as f64 / self.shifts as f64
+    }
+
+    // Minutes asleep per shift, counting the whole shift.
+    pub fn average_total_asleep(&self) -> f64 {
+        self.total_asleep as f64 / self.shifts as f64
+    }
+
+    // The fraction of their shifts the guard was asleep in this minute.
+    pub fn probability_asleep(&self, minute: Minute) -> f64 {
+        *self.histogram.get(&minute).unwrap_or(&0) as f64 / self.shifts as f64
+    }
+}
+
+// In guard id order, so anything printed from it comes out the same every time.
+pub type Stats = BTreeMap<GuardId, GuardStats>;
+
+pub fn guard_stats(shifts: &[Shift], window: Window) -> Stats {
+    let mut stats = Stats::new();
+    for shift in shifts {
+        let guard = stats.entry(shift.guard).or_insert_with(|| GuardStats {
+            shifts: 0,
+            minutes_asleep: 0,
+            total_asleep: 0,
+            longest_nap: 0,
+            histogram: HashMap::new(),
+            first_shift: shift.date,
+            last_shift: shift.date,
+        });
+        guard.shifts += 1;
+        guard.first_shift = guard.first_shift.min(shift.date);
+        guard.last_shift = guard.last_shift.max(shift.date);
+        for nap in &shift.naps {
+            guard.total_asleep += nap.minutes();
+            guard.longest_nap = guard.longest_nap.max(nap.minutes());
+        }
+    }
+    for (guard, histogram) in sleep::minute_counts(&sleep::naps(shifts), window) {
+        let guard = stats.get_mut(&guard).unwrap();
+        guard.minutes_asleep = histogram.values().sum();
+        guard.histogram = histogram;
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::read_events;
+    use crate::sleep::shifts;
+
+    #[test]
+    fn test_guard_stats() {
+        let input = "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] falls asleep\n[1518-11-01 00:25] wakes up\n[1518-11-01 00:30] falls asleep\n[1518-11-01 00:55] wakes up\n[1518-11-01 23:58] Guard #99 begins shift\n[1518-11-02 00:40] falls asleep\n[1518-11-02 00:50] wakes up\n[1518-11-03 00:05] Guard #10 begins shift\n[1518-11-03 00:24] falls asleep\n[1518-11-03 00:29] wakes up\n[1518-11-03 23:50] Guard #7 begins shift\n[1518-11-03 23:55] falls asleep\n[1518-11-04 00:05] wakes up";
+        let stats = guard_stats(&shifts(&read_events(input).unwrap()), Window::MidnightHour);
+        let ten = &stats[&10];
+        assert_eq!(
+            (2, 50, 25),
+            (ten.shifts, ten.minutes_asleep, ten.longest_nap)
+        );
+        assert_eq!(25.0, ten.average_asleep());
+        assert_eq!(1.0, ten.probability_asleep(24));
+        assert_eq!(0.5, ten.probability_asleep(5));
+        assert_eq!(0.0, ten.probability_asleep(59));
+        assert_eq!(
+            ("1518-11-01".to_string(), "1518-11-03".to_string()),
+            (ten.first_shift.to_string(), ten.last_shift.to_string())
+        );
+        // the nap from 23:55 only counts from midnight, but is still 10 minutes long
+        let seven = &stats[&7];
+        assert_eq!(
+            (5, 10, 10),
+            (seven.minutes_asleep, seven.total_asleep, seven.longest_nap)
+        );
+        assert_eq!(
+            (5.0, 10.0),
+            (seven.average_asleep(), seven.average_total_asleep())
+        );
+        assert_eq!(vec![7, 10, 99], stats.keys().cloned().collect::<Vec<_>>());
+    }
+}
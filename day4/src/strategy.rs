@@ -0,0 +1,219 @@
+// Ways of picking the guard and minute to sneak in at. The puzzle's two strategies are built in
+// alongside a couple more we use, and anything else only needs to implement Strategy.
+// Every strategy answers with a guard, a minute and the score that won it, plus anything that
+// scored the same and only lost on the tie break.
+
+use crate::rank::{self, Pick, TieBreak};
+use crate::stats::Stats;
+use crate::{GuardId, Minute};
+use std::collections::BTreeMap;
+
+#[derive(Debug, PartialEq)]
+pub struct Answer {
+    pub guard: GuardId,
+    pub minute: Minute,
+    pub score: u32,
+    pub tied_with: Vec<(GuardId, Minute)>,
+}
+
+pub trait Strategy {
+    // what it's called on the command line
+    fn name(&self) -> &'static str;
+    // what the score counts, for printing
+    fn score_meaning(&self) -> &'static str;
+    // None when nobody slept at all
+    fn choose(&self, stats: &Stats, ties: TieBreak) -> Option<Answer>;
+}
+
+// The guard's most slept minute, along with any others they slept in just as often.
+fn sleepiest_minute(stats: &Stats, guard: GuardId, ties: TieBreak) -> Option<Pick<Minute>> {
+    rank::sleepiest_minute(&stats[&guard].histogram, ties)
+}
+
+// Picks a guard by some score, then their most slept minute. Other guards on the same score are
+// ties (at their own most slept minute), as are other minutes the winner slept in as often.
+fn guard_then_minute(stats: &Stats, guard: Pick<GuardId>, ties: TieBreak) -> Option<Answer> {
+    let minute = sleepiest_minute(stats, guard.winner, ties)?;
+    let mut tied_with: Vec<(GuardId, Minute)> = minute
+        .tied_with
+        .iter()
+        .map(|&m| (guard.winner, m))
+        .collect();
+    for &other in &guard.tied_with {
+        if let Some(other_minute) = sleepiest_minute(stats, other, ties) {
+            tied_with.push((other, other_minute.winner));
+        }
+    }
+    Some(Answer {
+        guard: guard.winner,
+        minute: minute.winner,
+        score: guard.score,
+        tied_with,
+    })
+}
+
+// Strategy 1: the guard with the most minutes asleep, at the minute they're most often asleep.
+pub struct MostAsleep;
+
+impl Strategy for MostAsleep {
+    fn name(&self) -> &'static str {
+        "most-asleep"
+    }
+
+    fn score_meaning(&self) -> &'static str {
+        "minutes asleep in total"
+    }
+
+    fn choose(&self, stats: &Stats, ties: TieBreak) -> Option<Answer> {
+        guard_then_minute(stats, rank::sleepiest_guard(stats, ties)?, ties)
+    }
+}
+
+// Strategy 2: of all guards, the one most often asleep in the same minute.
+pub struct MostFrequentMinute;
+
+impl Strategy for MostFrequentMinute {
+    fn name(&self) -> &'static str {
+        "most-frequent-minute"
+    }
+
+    fn score_meaning(&self) -> &'static str {
+        "times asleep in that minute"
+    }
+
+    fn choose(&self, stats: &Stats, ties: TieBreak) -> Option<Answer> {
+        let pair = rank::most_frequent_pair(stats, ties)?;
+        Some(Answer {
+            guard: pair.winner.0,
+            minute: pair.winner.1,
+            score: pair.score,
+            tied_with: pair.tied_with,
+        })
+    }
+}
+
+// The guard who took the longest single nap, at the minute they're most often asleep. Only guards
+// who slept inside the window count, since the others have no minute to give.
+pub struct LongestNap;
+
+impl Strategy for LongestNap {
+    fn name(&self) -> &'static str {
+        "longest-nap"
+    }
+
+    fn score_meaning(&self) -> &'static str {
+        "minutes in their longest nap"
+    }
+
+    fn choose(&self, stats: &Stats, ties: TieBreak) -> Option<Answer> {
+        let naps = stats
+            .iter()
+            .filter(|(_, s)| !s.histogram.is_empty())
+            .map(|(&guard, s)| (guard, s.longest_nap));
+        guard_then_minute(stats, rank::best(naps, ties)?, ties)
+    }
+}
+
+// The minute the most different guards have been asleep in, and whichever of them was asleep in
+// it most often.
+pub struct MostGuards;
+
+impl Strategy for MostGuards {
+    fn name(&self) -> &'static str {
+        "most-guards"
+    }
+
+    fn score_meaning(&self) -> &'static str {
+        "different guards asleep in that minute"
+    }
+
+    fn choose(&self, stats: &Stats, ties: TieBreak) -> Option<Answer> {
+        let mut guards: BTreeMap<Minute, u32> = BTreeMap::new();
+        for guard_stats in stats.values() {
+            for &minute in guard_stats.histogram.keys() {
+                *guards.entry(minute).or_insert(0) += 1;
+            }
+        }
+        let minute = rank::best(guards, ties)?;
+        let guard_for = |minute: Minute| {
+            let counts = stats
+                .iter()
+                .filter_map(|(&guard, s)| Some((guard, *s.histogram.get(&minute)?)));
+            rank::best(counts, ties).unwrap()
+        };
+        let guard = guard_for(minute.winner);
+        let mut tied_with: Vec<(GuardId, Minute)> = guard
+            .tied_with
+            .iter()
+            .map(|&g| (g, minute.winner))
+            .collect();
+        for &other in &minute.tied_with {
+            tied_with.push((guard_for(other).winner, other));
+        }
+        Some(Answer {
+            guard: guard.winner,
+            minute: minute.winner,
+            score: minute.score,
+            tied_with,
+        })
+    }
+}
+
+pub const BUILT_IN: [&dyn Strategy; 4] =
+    [&MostAsleep, &MostFrequentMinute, &LongestNap, &MostGuards];
+
+// Looks a built-in strategy up by name; "1" and "2" are the puzzle's strategies.
+pub fn by_name(name: &str) -> Option<&'static dyn Strategy> {
+    let name = match name {
+        "1" => "most-asleep",
+        "2" => "most-frequent-minute",
+        name => name,
+    };
+    BUILT_IN
+        .iter()
+        .find(|strategy| strategy.name() == name)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::read_events;
+    use crate::sleep::{shifts, Window};
+    use crate::stats::guard_stats;
+    use crate::test_stats;
+
+    #[test]
+    fn test_built_in() {
+        let stats = test_stats();
+        let answer = |name| {
+            let answer = by_name(name)
+                .unwrap()
+                .choose(&stats, TieBreak::Lowest)
+                .unwrap();
+            (answer.guard, answer.minute, answer.score)
+        };
+        assert_eq!((10, 24, 50), answer("1"));
+        assert_eq!((99, 45, 3), answer("most-frequent-minute"));
+        assert_eq!((10, 24, 25), answer("longest-nap"));
+        // both guards have been asleep in every minute from 36 to 54, each of them once at 36
+        assert_eq!((10, 36, 2), answer("most-guards"));
+        assert!(by_name("3").is_none());
+        let stats = guard_stats(&[], Window::MidnightHour);
+        assert!(BUILT_IN
+            .iter()
+            .all(|s| s.choose(&stats, TieBreak::Lowest).is_none()));
+    }
+
+    #[test]
+    fn test_longest_nap_outside_window() {
+        // guard 7's half hour nap is all before midnight, so guard 10 has the longest one that counts
+        let input = "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] falls asleep\n[1518-11-01 00:25] wakes up\n[1518-11-01 22:00] Guard #7 begins shift\n[1518-11-01 22:10] falls asleep\n[1518-11-01 22:40] wakes up";
+        let stats = guard_stats(&shifts(&read_events(input).unwrap()), Window::MidnightHour);
+        let answer = LongestNap.choose(&stats, TieBreak::Lowest).unwrap();
+        assert_eq!((10, 5, 20), (answer.guard, answer.minute, answer.score));
+        let stats = guard_stats(&shifts(&read_events(input).unwrap()), Window::MinuteOfDay);
+        let answer = LongestNap.choose(&stats, TieBreak::Lowest).unwrap();
+        assert_eq!((7, 1330, 30), (answer.guard, answer.minute, answer.score));
+    }
+}
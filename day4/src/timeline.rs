@@ -0,0 +1,131 @@
+// The night-by-night table from the puzzle description, one row per shift with `#` for each
+// minute of the midnight hour the guard was asleep:
+//
+// Date   ID   Minute
+//             000000000011111111112222222222333333333344444444445555555555
+//             012345678901234567890123456789012345678901234567890123456789
+// 11-01  #10  .....####################.....#########################.....
+//
+// and the same thing as an SVG (or an HTML page around one) with each guard in their own colour.
+
+use crate::sleep::Shift;
+use crate::{GuardId, Minute};
+
+const CELL: usize = 10;
+const LABEL_WIDTH: usize = 120;
+const AWAKE: &str = "#eeeeee";
+
+fn id_width(shifts: &[Shift]) -> usize {
+    shifts
+        .iter()
+        .map(|shift| shift.guard.to_string().len() + 1)
+        .max()
+        .unwrap_or(0)
+        .max(2)
+}
+
+pub fn table(shifts: &[Shift]) -> String {
+    let width = id_width(shifts);
+    let mut out = format!("Date   {:<width$}  Minute\n", "ID", width = width);
+    let indent = " ".repeat(7 + width + 2);
+    let tens: String = (0..60).map(|m| (b'0' + m / 10) as char).collect();
+    let units: String = (0..60).map(|m| (b'0' + m % 10) as char).collect();
+    out.push_str(&format!("{}{}\n{}{}\n", indent, tens, indent, units));
+    for shift in shifts {
+        let minutes: String = (0..60)
+            .map(|m| if shift.asleep_at(m) { '#' } else { '.' })
+            .collect();
+        out.push_str(&format!(
+            "{:02}-{:02}  {:<width$}  {}\n",
+            shift.date.month,
+            shift.date.day,
+            format!("#{}", shift.guard),
+            minutes,
+            width = width
+        ));
+    }
+    out
+}
+
+// Spreads the guards around the colour wheel by the golden angle, so neighbours in id order still
+// get clearly different hues however many guards there are.
+fn colour(guards: &[GuardId], guard: GuardId) -> String {
+    let index = guards.binary_search(&guard).unwrap_or(0);
+    format!("hsl({}, 65%, 45%)", (index * 137) % 360)
+}
+
+pub fn svg(shifts: &[Shift]) -> String {
+    let mut guards: Vec<GuardId> = shifts.iter().map(|shift| shift.guard).collect();
+    guards.sort_unstable();
+    guards.dedup();
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"{}\">\n",
+        LABEL_WIDTH + 60 * CELL,
+        (shifts.len() + 1) * CELL,
+        CELL - 1
+    );
+    for minute in (0..60).step_by(5) {
+        out.push_str(&format!(
+            "  <text x=\"{}\" y=\"{}\">{:02}</text>\n",
+            LABEL_WIDTH + minute * CELL,
+            CELL - 1,
+            minute
+        ));
+    }
+    for (row, shift) in shifts.iter().enumerate() {
+        let y = (row + 1) * CELL;
+        let fill = colour(&guards, shift.guard);
+        out.push_str(&format!(
+            "  <text x=\"0\" y=\"{}\">{:02}-{:02} #{}</text>\n",
+            y + CELL - 1,
+            shift.date.month,
+            shift.date.day,
+            shift.guard
+        ));
+        for minute in 0..60 {
+            let asleep = shift.asleep_at(minute as Minute);
+            out.push_str(&format!(
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                LABEL_WIDTH + minute * CELL,
+                y,
+                CELL - 1,
+                CELL - 1,
+                if asleep { fill.as_str() } else { AWAKE }
+            ));
+        }
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+pub fn html(shifts: &[Shift]) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Guard shifts</title></head>\n<body>\n{}</body>\n</html>\n",
+        svg(shifts)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::read_events;
+    use crate::sleep::shifts;
+
+    #[test]
+    fn test_table() {
+        let input = "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] falls asleep\n[1518-11-01 00:25] wakes up\n[1518-11-01 00:30] falls asleep\n[1518-11-01 00:55] wakes up\n[1518-11-01 23:58] Guard #99 begins shift\n[1518-11-02 00:40] falls asleep\n[1518-11-02 00:50] wakes up";
+        let shifts = shifts(&read_events(input).unwrap());
+        assert_eq!(
+            "Date   ID   Minute\n\
+             \x20           000000000011111111112222222222333333333344444444445555555555\n\
+             \x20           012345678901234567890123456789012345678901234567890123456789\n\
+             11-01  #10  .....####################.....#########################.....\n\
+             11-02  #99  ........................................##########..........\n",
+            table(&shifts)
+        );
+        let svg = svg(&shifts);
+        assert_eq!(120, svg.matches("<rect").count());
+        assert_eq!(45 + 10, svg.matches("hsl(").count());
+        assert!(html(&shifts).contains("<svg"));
+    }
+}